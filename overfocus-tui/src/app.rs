@@ -1,28 +1,56 @@
-use std::time::{Duration, Instant};
+use std::{rc::Rc, time::Duration};
 
-use crossterm::event::{Event, self, KeyCode};
-use overfocus::logger::{Logger, LogKind, self};
-use tui::{backend::Backend, Terminal, layout::{Layout, Direction, Constraint, Alignment, Rect}, widgets::{Block, Borders, Paragraph}};
+use anyhow::Result;
+use chrono::Local;
 
-use self::{utils::draw_block_with_text, input::{UserInput, Target}, pomo_ui::{starter::PomodoroStarterUI, clock::PomodoroClockUI}, ui::{UI, UIContext}, styles::{info_log_style, warn_log_style, err_log_style, regular_style}, notifications::notify};
+use overfocus::{logger::{Logger, LogKind, self}, config::Config, import, report::ReportPeriod, pomodoro::Pomodoro, history::History, unwrap_err, log_warn};
+use tui::{backend::Backend, Terminal, layout::{Layout, Direction, Constraint, Alignment, Rect}, widgets::{Block, Borders, Paragraph}, text::Spans};
+
+use crate::cli::RunArgs;
+use self::{utils::draw_block_with_text, input::{UserInput, Target}, pomo_ui::{starter::PomodoroStarterUI, clock::PomodoroClockUI}, stats_ui::StatsUI, tasks_ui::TasksUI, inbox_ui::InboxUI, reflection_ui::ReflectionUI, export_ui::ExportUI, history_ui::HistoryUI, report_ui::ReportUI, achievements_ui::AchievementsUI, ui::{UI, UIContext}, theme::{border_style, info_log_style, warn_log_style, err_log_style, regular_style}, events::{Events, AppEvent}, notifications::notify, keymap::{Keymap, Action}, help_ui::HelpUI};
 
 mod pomo_ui {
     pub mod starter;
     pub mod clock;
 }
 
+mod widgets {
+    pub mod text_input;
+    pub mod big_clock;
+}
+
+mod stats_ui;
+mod tasks_ui;
+mod inbox_ui;
+mod reflection_ui;
+mod export_ui;
+mod history_ui;
+mod report_ui;
+mod achievements_ui;
+mod help_ui;
 mod utils;
 mod input;
+mod events;
+pub mod keymap;
 mod ui;
-mod styles;
+pub mod theme;
 mod notifications;
 
+/// Terminals shorter than this drop the header and shrink the log to a line
+const FULL_HEIGHT: u16 = 28;
+/// Terminals shorter than this drop the log as well
+const LOG_HEIGHT: u16 = 12;
+/// Terminals narrower or shorter than this show a single line
+const COMPACT_SIZE: (u16, u16) = (20, 8);
+
 pub struct App<B: Backend> {
     terminal: Terminal<B>,
+    args: RunArgs,
 }
 
 struct AppContext<B: Backend> {
     stack: Vec<Box<dyn UI<B>>>,
+    keymap: Rc<Keymap>,
 }
 
 impl<B: Backend> AppContext<B> {
@@ -30,7 +58,8 @@ impl<B: Backend> AppContext<B> {
         self.stack.last_mut().unwrap().as_mut()
     }
 
-    pub fn push<T: UI<B> + 'static>(&mut self, ui: T) {
+    pub fn push<T: UI<B> + 'static>(&mut self, mut ui: T) {
+        ui.handle_context(UIContext::Keymap(self.keymap.clone()));
         self.stack.push(Box::new(ui))
     }
 
@@ -41,61 +70,94 @@ impl<B: Backend> AppContext<B> {
 
 
 impl<B: Backend> App<B> {
-    pub fn new(terminal: Terminal<B>) -> Self {
-        Self { terminal }
+    pub fn new(terminal: Terminal<B>, args: RunArgs) -> Self {
+        Self { terminal, args }
     }
     
     /// Main function to run the application
-    pub fn run(&mut self, tick_rate: Duration) {
+    pub fn run(&mut self, tick_rate: Duration) -> Result<()> {
         let mut input = UserInput::None;
-        let mut ctx = AppContext { stack: vec![Box::new(PomodoroStarterUI::new())] };
 
         Logger::init();
+        let config = unwrap_err!(Config::load(), else => Config::default());
+        // A broken theme leaves the dark one in place
+        unwrap_err!(theme::init(&config));
+        // Same for a broken keymap, with the default keys
+        let keymap = unwrap_err!(Keymap::from_config(&config), else => Keymap::default_preset());
+        if config.watch_imports {
+            import::spawn_watcher();
+        }
 
-        // Burner read to remove any buffered input
-        event::read().unwrap();
+        let mut ctx = AppContext { stack: Vec::new(), keymap: Rc::new(keymap) };
+        ctx.push(PomodoroStarterUI::new(self.args.clone()));
+        let events = Events::new(tick_rate);
+        // A clock that was running when the app crashed carries on, and one that was killed is recorded as far as the log goes
+        if let Some(snapshot) = unwrap_err!(Pomodoro::take_snapshot(), else => None) {
+            ctx.push(PomodoroClockUI::restore(snapshot, events.sender()));
+        } else if let Some(session) = unwrap_err!(History::recover(), else => None) {
+            log_warn!(format!("The {:?} stage from {} never finished, it was recorded as stopped.", session.stage, session.start.with_timezone(&Local).format("%H:%M")));
+        }
 
-        let mut last_tick = Instant::now();
-        //let mut skip_this = false;
         loop {
-            self.terminal.draw(|f| Self::ui(&mut ctx, f, &mut input)).unwrap();
-
-            if last_tick.elapsed() >= tick_rate {
-                last_tick = Instant::now();
+            self.terminal.draw(|f| Self::ui(&mut ctx, f, &mut input))?;
+
+            // Actions that work everywhere fall to the app when the screen leaves them be
+            if let UserInput::Action(action) = input {
+                input = match action {
+                    Action::Quit => UserInput::Goto(Target::Quit),
+                    Action::Tasks => UserInput::Goto(Target::Tasks),
+                    Action::Stats => UserInput::Goto(Target::Stats),
+                    Action::Help => UserInput::Goto(Target::Help),
+                    _ => UserInput::None,
+                };
             }
 
             if let UserInput::Goto(target) = &input {
                 match target {
-                    Target::Pomodoro => ctx.push(PomodoroClockUI::new()),
+                    Target::Pomodoro(options) => ctx.push(PomodoroClockUI::new(options.clone(), events.sender())),
+                    Target::Stats => ctx.push(StatsUI::new()),
+                    Target::Tasks => ctx.push(TasksUI::new()),
+                    Target::Inbox => ctx.push(InboxUI::new()),
+                    Target::History => ctx.push(HistoryUI::new()),
+                    Target::Report => ctx.push(ReportUI::new(ReportPeriod::Week)),
+                    Target::Achievements => ctx.push(AchievementsUI::new()),
+                    Target::Export => ctx.push(ExportUI::new()),
+                    Target::Reflection(session) => ctx.push(ReflectionUI::new(*session)),
+                    Target::Help => {
+                        let actions = ctx.peek().actions();
+                        let help = HelpUI::new(&ctx.keymap, &actions);
+                        ctx.push(help);
+                    },
                     Target::PopStack => {
                         if let Some(data) = ctx.pop() {
                             ctx.peek().handle_context(data);
                         }
                     },
-                    Target::Quit => return,
+                    Target::Quit => {
+                        for ui in ctx.stack.iter_mut().rev() {
+                            ui.on_quit();
+                        }
+                        return Ok(());
+                    },
                 }
             }
 
+            // Screens get a frame to follow up on what the input did before waiting again
             if input.is_consumed() {
                 input = UserInput::None;
-                event::read().unwrap();
                 continue;
             }
 
-            let timeout = tick_rate.checked_sub(last_tick.elapsed()).unwrap_or(Duration::from_secs(0));
-            if !event::poll(timeout).unwrap() {
-                continue;
-            }
-
-            if let Event::Key(key) = event::read().unwrap() {
-                input = match key.code {
-                    KeyCode::Up => UserInput::Up,
-                    KeyCode::Down => UserInput::Down,
-                    KeyCode::Left => UserInput::Left,
-                    KeyCode::Right => UserInput::Right,
-                    KeyCode::Enter => UserInput::Enter,
-                    _ => UserInput::None
-                }
+            match events.next()? {
+                AppEvent::Key(key) => {
+                    let screen = ctx.peek();
+                    let (actions, typing) = (screen.actions(), screen.typing());
+                    input = ctx.keymap.input(key, &actions, typing);
+                },
+                // Starts over at the new size, so nothing drawn at the old one is left behind
+                AppEvent::Resize(width, height) => unwrap_err!(self.terminal.resize(Rect::new(0, 0, width, height))),
+                AppEvent::Timer | AppEvent::Tick => {},
+                AppEvent::Failed(e) => return Err(e),
             }
         }
     }
@@ -104,17 +166,42 @@ impl<B: Backend> App<B> {
         &mut self.terminal
     }
 
-    /// Main function to draw ui
+    /// Main function to draw ui, giving up the header and then the log as the terminal gets smaller
     fn ui(ctx: &mut AppContext<B>, frame: &mut tui::Frame<B>, input: &mut UserInput) {
+        let size = frame.size();
+        if size.width < COMPACT_SIZE.0 || size.height < COMPACT_SIZE.1 {
+            Self::draw_compact(ctx, frame, input);
+            Self::send_notification();
+            return;
+        }
+
+        let (header, log) = match size.height {
+            h if h >= FULL_HEIGHT => (3, 3),
+            h if h >= LOG_HEIGHT => (0, 1),
+            _ => (0, 0),
+        };
         let layout = Layout::default().margin(0).direction(Direction::Vertical).constraints([
-            Constraint::Length(3),
+            Constraint::Length(header),
             Constraint::Min(0),
-            Constraint::Length(3),
-        ]).split(frame.size());
+            Constraint::Length(log),
+        ]).split(size);
 
-        draw_block_with_text(" Overfocus | Pomodoro ", Alignment::Center, frame, layout[0]);
+        if header > 0 {
+            draw_block_with_text(" Overfocus | Pomodoro ", Alignment::Center, frame, layout[0]);
+        }
         ctx.peek().ui(frame, layout[1], input);
-        Self::draw_logger(frame, layout[2]);
+        if log > 0 {
+            Self::draw_logger(frame, layout[2]);
+        }
+        Self::send_notification();
+    }
+
+    /// Draws the screen as a single line
+    fn draw_compact(ctx: &mut AppContext<B>, frame: &mut tui::Frame<B>, input: &mut UserInput) {
+        // Screens handle their input while drawing, so they still get to, just with no room to draw in
+        ctx.peek().ui(frame, Rect::default(), input);
+        let line = ctx.peek().compact().unwrap_or_else(|| Spans::from("Overfocus"));
+        frame.render_widget(Paragraph::new(line).style(regular_style()), Rect { height: 1, ..frame.size() });
     }
 
     fn draw_logger(frame: &mut tui::Frame<B>, rect: Rect) {
@@ -130,12 +217,17 @@ impl<B: Backend> App<B> {
             LogKind::Err => err_log_style(),
         };
 
-        let block = Block::default().borders(Borders::ALL).style(regular_style());
-        let paragraph = Paragraph::new(str).alignment(Alignment::Left).block(block).style(style);
+        // Without room for the box the message is all there is
+        let mut paragraph = Paragraph::new(str).alignment(Alignment::Left).style(style);
+        if rect.height >= 3 {
+            paragraph = paragraph.block(Block::default().borders(Borders::ALL).border_style(border_style()).style(regular_style()));
+        }
         frame.render_widget(paragraph, rect);
+    }
 
+    fn send_notification() {
         if let Some(x) = Logger::consume_notification() {
-            notify("Overfocus", &x.0, matches!(x.1, logger::Duration::Long)).unwrap();
+            unwrap_err!(notify("Overfocus", &x.0, matches!(x.1, logger::Duration::Long)));
         }
     }   
 }
\ No newline at end of file
@@ -0,0 +1,71 @@
+use overfocus::{achievements::{Achievements, Milestone, Progress}, config::Config, days, unwrap_err};
+use tui::{backend::Backend, layout::{Rect, Alignment}, text::{Spans, Span}, widgets::{Block, Borders, Paragraph}};
+
+use crate::app::{utils::sub_rect, input::{UserInput, Target}, ui::UI, theme::{border_style, regular_style, highlight_style, info_log_style}};
+
+/// Screen showing the current streak and which milestones were reached
+pub struct AchievementsUI {
+    achievements: Achievements,
+    progress: Progress,
+    on_vacation: bool,
+}
+
+impl<B: Backend> UI<B> for AchievementsUI {
+    fn ui(&mut self, frame: &mut tui::Frame<B>, rect: Rect, input: &mut UserInput) {
+        input.consume_matches(|x| matches!(x, UserInput::Esc | UserInput::Enter), |input| *input = UserInput::Goto(Target::PopStack));
+
+        let rect = sub_rect(rect, (50, 14 + Milestone::ALL.len() as u16));
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ Achievements ] ").title_alignment(Alignment::Center).style(regular_style());
+        let paragraph = Paragraph::new(self.get_spans()).block(block).style(regular_style());
+        frame.render_widget(paragraph, rect);
+    }
+}
+
+impl AchievementsUI {
+    pub fn new() -> Self {
+        let today = days::today();
+        Self {
+            achievements: unwrap_err!(Achievements::load(), else => Achievements::default()),
+            progress: unwrap_err!(Progress::load(today), else => Progress::default()),
+            on_vacation: unwrap_err!(Config::load(), else => Config::default()).on_vacation(today),
+        }
+    }
+
+    fn get_spans(&self) -> Vec<Spans<'_>> {
+        let streak = match self.progress.streak {
+            1 => "1 day".to_string(),
+            n => format!("{} days", n),
+        };
+        let mut res = vec![
+            Spans::from(format!("Streak: {}{}", streak, if self.on_vacation { " (on vacation)" } else { "" })),
+            Spans::from(format!("Pomodoros: {}", self.progress.pomodoros)),
+            Spans::from(""),
+        ];
+
+        for milestone in Milestone::ALL {
+            match self.achievements.unlocked.get(&milestone) {
+                Some(at) => {
+                    let at = days::local_day(*at);
+                    res.push(Spans::from(Span::styled(format!("[x] {} ({})", milestone.name(), at), highlight_style())));
+                },
+                None => res.push(Spans::from(format!("[ ] {}", milestone.name()))),
+            }
+            res.push(Spans::from(Span::styled(format!("    {}", milestone.description()), info_log_style())));
+        }
+
+        if let Some(next) = self.next_count_milestone() {
+            res.push(Spans::from(""));
+            res.push(Spans::from(next));
+        }
+        res
+    }
+
+    /// How far the next pomodoro count milestone is
+    fn next_count_milestone(&self) -> Option<String> {
+        let target = [1, 100, 1000].into_iter().find(|x| self.progress.pomodoros < *x)?;
+        match target - self.progress.pomodoros {
+            1 => Some("1 pomodoro to go".to_string()),
+            left => Some(format!("{} pomodoros to go for {}", left, target)),
+        }
+    }
+}
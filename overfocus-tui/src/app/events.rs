@@ -0,0 +1,63 @@
+use std::{sync::mpsc::{self, Sender, Receiver}, thread, time::Duration};
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyEvent, KeyEventKind};
+
+/// Everything the app wakes up for
+pub enum AppEvent {
+    Key(KeyEvent),
+    Resize(u16, u16),
+    /// What the pomodoro clock shows changed
+    Timer,
+    /// Redraws now and then for what changes on its own, like logs from other threads
+    Tick,
+    /// Reading the terminal failed, which ends the app
+    Failed(anyhow::Error),
+}
+
+/// Terminal input, timer changes and redraw ticks merged into a channel, each fed by its own thread
+pub struct Events {
+    tx: Sender<AppEvent>,
+    rx: Receiver<AppEvent>,
+}
+
+impl Events {
+    pub fn new(tick_rate: Duration) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        let input_tx = tx.clone();
+        thread::spawn(move || loop {
+            let event = match event::read() {
+                // Only presses count, some platforms also report releases
+                Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => AppEvent::Key(key),
+                Ok(Event::Resize(width, height)) => AppEvent::Resize(width, height),
+                Ok(_) => continue,
+                Err(e) => AppEvent::Failed(e.into()),
+            };
+            let failed = matches!(event, AppEvent::Failed(_));
+            if input_tx.send(event).is_err() || failed {
+                return;
+            }
+        });
+
+        let tick_tx = tx.clone();
+        thread::spawn(move || loop {
+            thread::sleep(tick_rate);
+            if tick_tx.send(AppEvent::Tick).is_err() {
+                return;
+            }
+        });
+
+        Self { tx, rx }
+    }
+
+    /// Waits for the next event
+    pub fn next(&self) -> Result<AppEvent> {
+        Ok(self.rx.recv()?)
+    }
+
+    /// Lets other threads wake the app up
+    pub fn sender(&self) -> Sender<AppEvent> {
+        self.tx.clone()
+    }
+}
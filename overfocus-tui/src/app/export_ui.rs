@@ -0,0 +1,115 @@
+use std::rc::Rc;
+
+use overfocus::{export::{self, ExportFormat, ExportFilter}, stats::DateRange, tags::{parse_tags, display_tags}, log_info, unwrap_err};
+use tui::{backend::Backend, layout::{Rect, Alignment}, text::Spans, widgets::{Block, Borders, Paragraph}};
+
+use crate::app::{utils::{sub_rect, handle_menu_input, menu_spans, hint_spans}, input::{UserInput, Target}, ui::{UI, UIContext}, keymap::{Keymap, Action}, theme::{border_style, regular_style}, widgets::text_input::{TextInput, TextInputEvent}};
+
+/// Ranges that can be picked for an export, in days, with `None` being the whole history
+const RANGES: [Option<i64>; 4] = [None, Some(7), Some(30), Some(365)];
+
+/// Screen to export the history to a file in the data directory
+pub struct ExportUI {
+    selected: usize,
+    range: usize,
+    /// Only sessions with all of these are exported
+    tags: Vec<String>,
+    editing_tags: Option<TextInput>,
+    keymap: Rc<Keymap>,
+}
+
+impl<B: Backend> UI<B> for ExportUI {
+    fn ui(&mut self, frame: &mut tui::Frame<B>, rect: Rect, input: &mut UserInput) {
+        self.handle_tags_input(input);
+        let entries = ExportFormat::ALL.len() + 1;
+        handle_menu_input(input, &mut self.selected, entries);
+        input.consume_matches(|x| matches!(x, UserInput::Left), |_| self.range = (self.range + RANGES.len() - 1) % RANGES.len());
+        input.consume_matches(|x| matches!(x, UserInput::Right), |_| self.range = (self.range + 1) % RANGES.len());
+        if input.consume_matches(|x| matches!(x, UserInput::Esc), |input| *input = UserInput::Goto(Target::PopStack)).is_some() {
+            return;
+        }
+        if input.consume_matches(|x| matches!(x, UserInput::Enter), |input| {
+            match ExportFormat::ALL.get(self.selected) {
+                Some(format) => self.export(*format),
+                None => *input = UserInput::Goto(Target::PopStack),
+            }
+        }).is_some() { return }
+
+        let rect = sub_rect(rect, (30, 13));
+        let tags = if self.tags.is_empty() { "Any".to_string() } else { display_tags(&self.tags) };
+        let mut text = vec![
+            Spans::from(format!("Range: <{}>", Self::display_range(RANGES[self.range]))),
+            Spans::from(format!("Tags: {}", tags)),
+            Spans::from(""),
+        ];
+        let entries = ExportFormat::ALL.iter().map(|x| x.name().to_string()).chain(["Back".to_string()]);
+        text.extend(menu_spans(entries, self.selected));
+        text.push(Spans::from(""));
+        text.push(hint_spans([
+            self.keymap.hint(&[Action::Left, Action::Right], "range", false),
+            Some("t:tags".to_string()),
+            self.keymap.hint(&[Action::Confirm], "export", false),
+        ]));
+
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ Export ] ").title_alignment(Alignment::Center).style(regular_style());
+        let paragraph = Paragraph::new(text).block(block).style(regular_style());
+        frame.render_widget(paragraph, rect);
+
+        if let Some(text_input) = &self.editing_tags {
+            text_input.render(frame, sub_rect(rect, (26, 3)));
+        }
+    }
+
+    fn typing(&self) -> bool {
+        self.editing_tags.is_some()
+    }
+
+    fn handle_context(&mut self, ctx: UIContext) {
+        if let UIContext::Keymap(keymap) = ctx {
+            self.keymap = keymap;
+        }
+    }
+}
+
+impl ExportUI {
+    pub fn new() -> Self {
+        Self { selected: 0, range: 0, tags: Vec::new(), editing_tags: None, keymap: Rc::new(Keymap::default_preset()) }
+    }
+
+    /// Opens the tag filter prompt with `t`, leaving it the only one taking input while open
+    fn handle_tags_input(&mut self, input: &mut UserInput) {
+        if let Some(text_input) = &mut self.editing_tags {
+            match text_input.handle_input(input) {
+                Some(TextInputEvent::Submitted(text)) => {
+                    self.tags = parse_tags(&text);
+                    self.editing_tags = None;
+                },
+                Some(_) => self.editing_tags = None,
+                None => {},
+            }
+            // Whatever the prompt didn't take isn't for the menu behind it
+            if *input != UserInput::None {
+                *input = UserInput::Consumed;
+            }
+            return;
+        }
+
+        input.consume_matches(|x| matches!(x, UserInput::Char('t')), |_| {
+            self.editing_tags = Some(TextInput::new("Tags").with_value(display_tags(&self.tags)));
+        });
+    }
+
+    fn export(&self, format: ExportFormat) {
+        let filter = ExportFilter { range: RANGES[self.range].map(DateRange::last_days), tags: self.tags.clone() };
+        if let Some(path) = unwrap_err!(export::export_to_data_dir(format, &filter).map(Some), else => None) {
+            log_info!(format!("Exported to {}", path.display()));
+        }
+    }
+
+    fn display_range(days: Option<i64>) -> String {
+        match days {
+            Some(days) => format!("Last {} days", days),
+            None => "All".to_string(),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use tui::{backend::Backend, layout::{Rect, Alignment}, text::{Spans, Span}, widgets::{Block, Borders, Paragraph}};
+
+use crate::app::{utils::{sub_rect, hint_spans}, input::{UserInput, Target}, keymap::{Keymap, Action}, ui::UI, theme::{border_style, regular_style, highlight_style}};
+
+const WIDTH: u16 = 56;
+/// Room taken by the keys of an action
+const KEYS_WIDTH: usize = 16;
+
+/// Screen listing the keys of the screen it was opened from, then the ones that work everywhere
+pub struct HelpUI {
+    /// Sections with the keys and the description of every action in them
+    sections: Vec<(&'static str, Vec<(String, &'static str)>)>,
+    /// Hint for leaving, with the keys of the keymap
+    back: Option<String>,
+}
+
+impl<B: Backend> UI<B> for HelpUI {
+    fn ui(&mut self, frame: &mut tui::Frame<B>, rect: Rect, input: &mut UserInput) {
+        input.consume_matches(|x| matches!(x, UserInput::Esc | UserInput::Enter | UserInput::Action(Action::Help)), |input| *input = UserInput::Goto(Target::PopStack));
+
+        let lines = self.sections.iter().map(|(_, actions)| actions.len() + 2).sum::<usize>();
+        let rect = sub_rect(rect, (WIDTH, lines as u16 + 2));
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ Keys ] ").title_alignment(Alignment::Center).style(regular_style());
+        let paragraph = Paragraph::new(self.get_spans()).block(block).style(regular_style());
+        frame.render_widget(paragraph, rect);
+    }
+}
+
+impl HelpUI {
+    /// Lists the keys for the actions a screen takes, and the ones every screen does
+    pub fn new(keymap: &Keymap, actions: &[Action]) -> Self {
+        let describe = |actions: &[Action]| -> Vec<(String, &'static str)> {
+            actions.iter()
+                .map(|action| (keymap.keys(*action).iter().map(|x| x.to_string()).collect::<Vec<_>>().join(" "), action.description()))
+                // Actions without keys can't be done, so they aren't worth listing
+                .filter(|(keys, _)| !keys.is_empty())
+                .collect()
+        };
+
+        let mut sections = Vec::new();
+        if !actions.is_empty() {
+            sections.push(("Here", describe(actions)));
+        }
+        sections.push(("Moving around", describe(&Action::NAVIGATION)));
+        sections.push(("Everywhere", describe(&Action::GLOBAL)));
+        Self { sections, back: keymap.hint(&[Action::Back], "back", false) }
+    }
+
+    fn get_spans(&self) -> Vec<Spans<'_>> {
+        let mut res = Vec::new();
+        for (title, actions) in &self.sections {
+            res.push(Spans::from(Span::styled(*title, highlight_style())));
+            for (keys, description) in actions {
+                res.push(Spans::from(format!("  {:<width$} {}", keys, description, width = KEYS_WIDTH)));
+            }
+            res.push(Spans::from(""));
+        }
+        res.pop();
+        res.push(hint_spans([self.back.clone()]));
+        res
+    }
+}
@@ -0,0 +1,214 @@
+use std::rc::Rc;
+
+use chrono::{DateTime, Utc, Local};
+use overfocus::{history::{History, Session, Outcome}, edits::{self, SessionEdit}, pomodoro::PomodoroStage, tasks::TaskList, tags::{parse_tags, display_tags}, days, log_info, log_warn, unwrap_err};
+use tui::{backend::Backend, layout::{Rect, Alignment}, text::{Spans, Span}, widgets::{Block, Borders, Paragraph}};
+use uuid::Uuid;
+
+use crate::app::{utils::{sub_rect, handle_menu_input, menu_spans, truncate, hint_spans}, input::{UserInput, Target}, ui::{UI, UIContext}, keymap::{Keymap, Action}, theme::{border_style, regular_style, info_log_style}, widgets::text_input::{TextInput, TextInputEvent}};
+
+const WIDTH: u16 = 70;
+const HEIGHT: u16 = 20;
+
+enum Edit { Times(Uuid), Split(Uuid), Project(Uuid), Tags(Uuid) }
+
+/// Screen to correct past sessions, most recent first
+pub struct HistoryUI {
+    sessions: Vec<Session>,
+    tasks: TaskList,
+    selected: usize,
+    editing: Option<(Edit, TextInput)>,
+    keymap: Rc<Keymap>,
+}
+
+impl<B: Backend> UI<B> for HistoryUI {
+    fn ui(&mut self, frame: &mut tui::Frame<B>, rect: Rect, input: &mut UserInput) {
+        self.handle_input(input);
+
+        let rect = sub_rect(rect, (WIDTH, HEIGHT));
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ History ] ").title_alignment(Alignment::Center).style(regular_style());
+        let paragraph = Paragraph::new(self.get_spans()).block(block).style(regular_style());
+        frame.render_widget(paragraph, rect);
+
+        if let Some((_, text_input)) = &self.editing {
+            text_input.render(frame, sub_rect(rect, (WIDTH - 20, 3)));
+        }
+    }
+
+    fn typing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    fn handle_context(&mut self, ctx: UIContext) {
+        if let UIContext::Keymap(keymap) = ctx {
+            self.keymap = keymap;
+        }
+    }
+}
+
+impl HistoryUI {
+    pub fn new() -> Self {
+        let mut res = Self { sessions: Vec::new(), tasks: TaskList::default(), selected: 0, editing: None, keymap: Rc::new(Keymap::default_preset()) };
+        res.reload();
+        res
+    }
+
+    fn handle_input(&mut self, input: &mut UserInput) {
+        if let Some((_, text_input)) = &mut self.editing {
+            match text_input.handle_input(input) {
+                Some(TextInputEvent::Submitted(text)) => {
+                    let (edit, _) = self.editing.take().unwrap();
+                    self.submit(edit, text);
+                },
+                Some(TextInputEvent::Cancelled) => self.editing = None,
+                None => {},
+            }
+            return;
+        }
+
+        handle_menu_input(input, &mut self.selected, self.sessions.len());
+        input.consume_matches(|x| matches!(x, UserInput::Left), |_| self.cycle_task(false));
+        input.consume_matches(|x| matches!(x, UserInput::Right), |_| self.cycle_task(true));
+        if input.consume_matches(|x| matches!(x, UserInput::Esc), |input| *input = UserInput::Goto(Target::PopStack)).is_some() {
+            return;
+        }
+
+        let UserInput::Char(key) = *input else { return };
+        *input = UserInput::Consumed;
+
+        if key == 'u' {
+            if let Some(description) = unwrap_err!(edits::undo().map(Some), else => None) {
+                log_info!(format!("Undid: {}.", description));
+            }
+            return self.reload();
+        }
+
+        let Some(session) = self.sessions.get(self.selected) else { return };
+        let id = session.id;
+        match key {
+            'e' => {
+                let times = format!("{}-{}", local_time(session, session.start), local_time(session, session.end));
+                self.editing = Some((Edit::Times(id), TextInput::new("Start-end (HH:MM)").with_value(times)));
+            },
+            's' => {
+                let middle = session.start + (session.end - session.start) / 2;
+                self.editing = Some((Edit::Split(id), TextInput::new("Split at (HH:MM)").with_value(local_time(session, middle))));
+            },
+            'p' => {
+                let project = session.project.clone().unwrap_or_default();
+                self.editing = Some((Edit::Project(id), TextInput::new("Project").with_value(project)));
+            },
+            't' => self.editing = Some((Edit::Tags(id), TextInput::new("Tags").with_value(display_tags(&session.tags)))),
+            // Merges with the previous session, which is the one below
+            'm' => match self.sessions.get(self.selected + 1) {
+                Some(previous) => {
+                    unwrap_err!(edits::merge(previous.id, id));
+                    self.reload();
+                },
+                None => log_warn!("There's no earlier session to merge with."),
+            },
+            'd' => {
+                unwrap_err!(edits::delete(id));
+                self.reload();
+            },
+            _ => {},
+        }
+    }
+
+    /// Applies the text of a finished prompt
+    fn submit(&mut self, edit: Edit, text: String) {
+        let id = match edit {
+            Edit::Times(id) | Edit::Split(id) | Edit::Project(id) | Edit::Tags(id) => id,
+        };
+        let Some(start) = self.sessions.iter().find(|x| x.id == id).map(|x| x.start) else { return };
+        let res = match edit {
+            Edit::Times(id) => {
+                let times = text.split_once('-').map(|(from, to)| (days::parse_time(from, start), days::parse_time(to, start)));
+                let Some((Some(from), Some(to))) = times else {
+                    log_warn!("Expected `HH:MM-HH:MM`.");
+                    return;
+                };
+                edits::edit(id, SessionEdit { start: Some(from), end: Some(to), ..Default::default() })
+            },
+            Edit::Split(id) => {
+                let Some(at) = days::parse_time(&text, start) else {
+                    log_warn!("Expected `HH:MM`.");
+                    return;
+                };
+                edits::split(id, at).map(|_| ())
+            },
+            Edit::Project(id) => {
+                let project = if text.is_empty() { None } else { Some(text) };
+                edits::edit(id, SessionEdit { project: Some(project), ..Default::default() })
+            },
+            Edit::Tags(id) => edits::edit(id, SessionEdit { tags: Some(parse_tags(&text)), ..Default::default() }),
+        };
+        unwrap_err!(res);
+        self.reload();
+    }
+
+    /// Moves the selected session to the next or previous task, going through no task at all
+    fn cycle_task(&mut self, forward: bool) {
+        let Some(session) = self.sessions.get(self.selected) else { return };
+        let mut options: Vec<Option<Uuid>> = vec![None];
+        options.extend(self.tasks.tasks.iter().map(|x| Some(x.id)));
+
+        let current = options.iter().position(|x| *x == session.task).unwrap_or(0);
+        let next = if forward { (current + 1) % options.len() } else { (current + options.len() - 1) % options.len() };
+        unwrap_err!(edits::edit(session.id, SessionEdit { task: Some(options[next]), ..Default::default() }));
+        self.reload();
+    }
+
+    fn reload(&mut self) {
+        let history = unwrap_err!(History::load(), else => History::default());
+        self.sessions = history.sessions.into_iter().rev().collect();
+        self.tasks = unwrap_err!(TaskList::load(), else => TaskList::default());
+        self.selected = self.selected.min(self.sessions.len().saturating_sub(1));
+    }
+
+    fn get_spans(&self) -> Vec<Spans<'_>> {
+        // Scrolls so the selected session is always visible
+        let visible = (HEIGHT - 5) as usize;
+        let offset = (self.selected + 1).saturating_sub(visible);
+
+        let entries = self.sessions.iter().skip(offset).take(visible).map(|session| {
+            let stage = match session.stage {
+                PomodoroStage::Work => "W",
+                PomodoroStage::ShortBreak => "B",
+                PomodoroStage::LongBreak => "L",
+            };
+            let done = if session.outcome == Outcome::Completed { " " } else { "!" };
+            let task = session.task.and_then(|id| self.tasks.get(id)).map(|x| x.title.as_str()).unwrap_or("");
+            let project = session.project.as_ref().map(|x| format!(" +{}", x)).unwrap_or_default();
+            let tags: String = session.tags.iter().map(|x| format!(" @{}", x)).collect();
+            let start = session.start.with_timezone(&Local);
+            format!("{}{} {} {}-{} {:>3}m {}", stage, done, start.format("%d/%m"), start.format("%H:%M"), local_time(session, session.end),
+                session.focus_secs / 60, truncate(format!("{}{}{}", task, project, tags).trim_start(), (WIDTH - 32) as usize))
+        });
+
+        let mut res = if self.sessions.is_empty() {
+            vec![Spans::from("No sessions yet.")]
+        } else {
+            menu_spans(entries, self.selected - offset)
+        };
+
+        res.resize(visible + 1, Spans::from(""));
+        res.push(hint_spans([
+            Some("e:times s:split m:merge".to_string()),
+            self.keymap.hint(&[Action::Left, Action::Right], "task", false),
+            Some("p:project t:tags".to_string()),
+        ]));
+        res.push(Spans::from(Span::styled("d:delete u:undo  (! marks unfinished stages)", info_log_style())));
+        res
+    }
+}
+
+/// `HH:MM` of a time, with the date too if it's not on the day the session started
+fn local_time(session: &Session, time: DateTime<Utc>) -> String {
+    let time = time.with_timezone(&Local);
+    if time.date_naive() == session.start.with_timezone(&Local).date_naive() {
+        time.format("%H:%M").to_string()
+    } else {
+        time.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
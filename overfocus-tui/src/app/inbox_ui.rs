@@ -0,0 +1,94 @@
+use std::rc::Rc;
+
+use chrono::Local;
+use overfocus::{inbox::Inbox, log_info, unwrap_err};
+use tui::{backend::Backend, layout::{Rect, Alignment}, text::Spans, widgets::{Block, Borders, Paragraph}};
+
+use crate::app::{utils::{sub_rect, handle_menu_input, menu_spans, truncate, hint_spans}, input::{UserInput, Target}, ui::{UI, UIContext}, keymap::{Keymap, Action}, theme::{border_style, regular_style}};
+
+const WIDTH: u16 = 60;
+const HEIGHT: u16 = 16;
+
+/// Screen to review the thoughts captured during work
+pub struct InboxUI {
+    inbox: Inbox,
+    selected: usize,
+    keymap: Rc<Keymap>,
+}
+
+impl<B: Backend> UI<B> for InboxUI {
+    fn ui(&mut self, frame: &mut tui::Frame<B>, rect: Rect, input: &mut UserInput) {
+        self.handle_input(input);
+
+        let rect = sub_rect(rect, (WIDTH, HEIGHT));
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ Inbox ] ").title_alignment(Alignment::Center).style(regular_style());
+        let paragraph = Paragraph::new(self.get_spans()).block(block).style(regular_style());
+        frame.render_widget(paragraph, rect);
+    }
+
+    fn get_context(&self) -> Option<UIContext> {
+        Some(UIContext::Inbox)
+    }
+
+    fn handle_context(&mut self, ctx: UIContext) {
+        if let UIContext::Keymap(keymap) = ctx {
+            self.keymap = keymap;
+        }
+    }
+}
+
+impl InboxUI {
+    pub fn new() -> Self {
+        let inbox = unwrap_err!(Inbox::load(), else => Inbox::default());
+        Self { inbox, selected: 0, keymap: Rc::new(Keymap::default_preset()) }
+    }
+
+    fn handle_input(&mut self, input: &mut UserInput) {
+        handle_menu_input(input, &mut self.selected, self.inbox.entries.len());
+        if input.consume_matches(|x| matches!(x, UserInput::Esc), |input| *input = UserInput::Goto(Target::PopStack)).is_some() {
+            return;
+        }
+
+        let Some(id) = self.inbox.entries.get(self.selected).map(|x| x.id) else { return };
+        if input.consume_matches(|x| matches!(x, UserInput::Enter), |_| ()).is_some() {
+            if unwrap_err!(Inbox::into_task(id), else => None).is_some() {
+                log_info!("Inbox entry moved to the task list.");
+            }
+            self.reload();
+        }
+        if input.consume_matches(|x| matches!(x, UserInput::Char('d')), |_| ()).is_some() {
+            unwrap_err!(Inbox::remove(id));
+            self.reload();
+        }
+    }
+
+    fn reload(&mut self) {
+        self.inbox = unwrap_err!(Inbox::load(), else => Inbox::default());
+        self.selected = self.selected.min(self.inbox.entries.len().saturating_sub(1));
+    }
+
+    fn get_spans(&self) -> Vec<Spans<'_>> {
+        // Scrolls so the selected entry is always visible
+        let visible = (HEIGHT - 4) as usize;
+        let offset = (self.selected + 1).saturating_sub(visible);
+
+        let entries = self.inbox.entries.iter().skip(offset).take(visible).map(|entry| {
+            let at = entry.at.with_timezone(&Local).format("%d/%m %H:%M");
+            format!("{} {}", at, truncate(&entry.text, (WIDTH - 15) as usize))
+        });
+
+        let mut res = if self.inbox.entries.is_empty() {
+            vec![Spans::from("Inbox is empty.")]
+        } else {
+            menu_spans(entries, self.selected - offset)
+        };
+
+        res.resize(visible + 1, Spans::from(""));
+        res.push(hint_spans([
+            self.keymap.hint(&[Action::Confirm], "move to tasks", false),
+            Some("d:delete".to_string()),
+            self.keymap.hint(&[Action::Back], "back", false),
+        ]));
+        res
+    }
+}
@@ -1,8 +1,17 @@
+use crossterm::event::{KeyEvent, KeyCode};
+use overfocus::pomodoro::PomodoroOptions;
+use uuid::Uuid;
+
+use super::keymap::Action;
+
 #[derive(PartialEq, Eq)]
 pub enum UserInput {
     None,
     Up, Right, Left, Down,
-    Enter,
+    Enter, Esc, Backspace,
+    Char(char),
+    /// A bound key that isn't for moving around
+    Action(Action),
 
     /// Defines a redirection to another part of the ui
     Goto(Target),
@@ -11,7 +20,18 @@ pub enum UserInput {
 
 #[derive(PartialEq, Eq)]
 pub enum Target {
-    Pomodoro,
+    Pomodoro(PomodoroOptions),
+    Stats,
+    Tasks,
+    Inbox,
+    History,
+    Report,
+    Achievements,
+    Export,
+    /// The keys of the screen on top
+    Help,
+    /// Reflection about the work session with the given id
+    Reflection(Uuid),
     PopStack,
     Quit,
 }
@@ -28,4 +48,35 @@ impl UserInput {
     pub fn is_consumed(&self) -> bool {
         matches!(self, UserInput::Goto(_) | UserInput::Consumed)
     }
-}
\ No newline at end of file
+}
+
+impl From<Action> for UserInput {
+    fn from(action: Action) -> Self {
+        match action {
+            Action::Up => UserInput::Up,
+            Action::Down => UserInput::Down,
+            Action::Left => UserInput::Left,
+            Action::Right => UserInput::Right,
+            Action::Confirm => UserInput::Enter,
+            Action::Back => UserInput::Esc,
+            action => UserInput::Action(action),
+        }
+    }
+}
+
+/// The key as it is, for typing and for the commands of screens
+impl From<KeyEvent> for UserInput {
+    fn from(key: KeyEvent) -> Self {
+        match key.code {
+            KeyCode::Up => UserInput::Up,
+            KeyCode::Down => UserInput::Down,
+            KeyCode::Left => UserInput::Left,
+            KeyCode::Right => UserInput::Right,
+            KeyCode::Enter => UserInput::Enter,
+            KeyCode::Esc => UserInput::Esc,
+            KeyCode::Backspace => UserInput::Backspace,
+            KeyCode::Char(c) => UserInput::Char(c),
+            _ => UserInput::None
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use std::fmt::Display;
+
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use overfocus::config::Config;
+use thiserror::Error;
+
+use super::input::UserInput;
+
+/// Keymaps that can be picked in the config, the first being the default
+pub const PRESETS: [&str; 2] = ["default", "vim"];
+
+#[derive(Error, Debug)]
+pub enum KeymapError {
+    #[error("No keymap is called `{0}`, expected `default` or `vim`")]
+    UnknownPreset(String),
+    #[error("Unknown action `{0}`")]
+    UnknownAction(String),
+    #[error("Invalid key `{0}`")]
+    InvalidKey(String),
+}
+
+/// Something a key can be bound to
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Up, Down, Left, Right, Confirm, Back,
+    PauseResume, Stop, Skip, ToggleTime,
+    InternalInterruption, ExternalInterruption, Capture, Review,
+    Quit, Tasks, Stats, Help,
+}
+
+impl Action {
+    pub const ALL: [Action; 18] = [
+        Action::Up, Action::Down, Action::Left, Action::Right, Action::Confirm, Action::Back,
+        Action::PauseResume, Action::Stop, Action::Skip, Action::ToggleTime,
+        Action::InternalInterruption, Action::ExternalInterruption, Action::Capture, Action::Review,
+        Action::Quit, Action::Tasks, Action::Stats, Action::Help,
+    ];
+
+    /// Moving around works on every screen
+    pub const NAVIGATION: [Action; 6] = [Action::Up, Action::Down, Action::Left, Action::Right, Action::Confirm, Action::Back];
+
+    /// Handled by the app on any screen that doesn't handle them itself
+    pub const GLOBAL: [Action; 4] = [Action::Quit, Action::Tasks, Action::Stats, Action::Help];
+
+    /// Name used in the config
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Confirm => "confirm",
+            Action::Back => "back",
+            Action::PauseResume => "pause",
+            Action::Stop => "stop",
+            Action::Skip => "skip",
+            Action::ToggleTime => "time",
+            Action::InternalInterruption => "internal",
+            Action::ExternalInterruption => "external",
+            Action::Capture => "capture",
+            Action::Review => "review",
+            Action::Quit => "quit",
+            Action::Tasks => "tasks",
+            Action::Stats => "stats",
+            Action::Help => "help",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Up => "Move up",
+            Action::Down => "Move down",
+            Action::Left => "Move left, or change the value",
+            Action::Right => "Move right, or change the value",
+            Action::Confirm => "Pick the selected entry",
+            Action::Back => "Go back",
+            Action::PauseResume => "Pause or resume the clock",
+            Action::Stop => "Stop the clock",
+            Action::Skip => "Skip to the next stage",
+            Action::ToggleTime => "Show the time left or spent",
+            Action::InternalInterruption => "Log an internal interruption",
+            Action::ExternalInterruption => "Log an external interruption",
+            Action::Capture => "Capture a thought to the inbox",
+            Action::Review => "Review the inbox, during breaks",
+            Action::Quit => "Quit",
+            Action::Tasks => "Open the tasks",
+            Action::Stats => "Open the stats",
+            Action::Help => "Show the keys",
+        }
+    }
+
+    fn parse(name: &str) -> Result<Self> {
+        Ok(Self::ALL.into_iter().find(|x| x.name() == name).ok_or_else(|| KeymapError::UnknownAction(name.to_string()))?)
+    }
+}
+
+/// A key along with the modifiers held with it
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    /// Shift is already in the case of characters, so it's left out for them,
+    /// and terminals report shift+tab as a key of its own
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        match code {
+            KeyCode::Char(_) => Self { code, modifiers: modifiers - KeyModifiers::SHIFT },
+            KeyCode::BackTab => Self { code: KeyCode::Tab, modifiers: modifiers | KeyModifiers::SHIFT },
+            _ => Self { code, modifiers },
+        }
+    }
+
+    /// Parses keys like `q`, `space`, `ctrl+c` or `shift+tab`
+    fn parse(text: &str) -> Result<Self> {
+        let invalid = || KeymapError::InvalidKey(text.to_string());
+        // `+` on its own is a key, not a separator
+        let (modifiers, key) = match text.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None => text.rsplit_once('+').filter(|(_, key)| !key.is_empty()).unwrap_or(("", text)),
+        };
+
+        let mut res = KeyModifiers::NONE;
+        for modifier in modifiers.split('+').filter(|x| !x.is_empty()) {
+            res |= match modifier.to_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => Err(invalid())?,
+            };
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "space" => KeyCode::Char(' '),
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "backspace" => KeyCode::Backspace,
+            "tab" => KeyCode::Tab,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "delete" => KeyCode::Delete,
+            name if name.len() > 1 && name.starts_with('f') => KeyCode::F(name[1..].parse().map_err(|_| invalid())?),
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    // Terminals send the shifted character, so that's what shift picks
+                    (Some(c), None) if res.contains(KeyModifiers::SHIFT) => KeyCode::Char(c.to_uppercase().next().unwrap_or(c)),
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => Err(invalid())?,
+                }
+            },
+        };
+        Ok(Self::new(code, res))
+    }
+
+    /// Keys held with ctrl or alt, which reach the actions even while typing
+    fn is_shortcut(&self) -> bool {
+        self.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+
+    /// The key a text box takes as it is for moving around
+    fn typed(action: Action) -> Option<Self> {
+        let code = match action {
+            Action::Up => KeyCode::Up,
+            Action::Down => KeyCode::Down,
+            Action::Left => KeyCode::Left,
+            Action::Right => KeyCode::Right,
+            Action::Confirm => KeyCode::Enter,
+            Action::Back => KeyCode::Esc,
+            _ => return None,
+        };
+        Some(Self::new(code, KeyModifiers::NONE))
+    }
+
+    /// Name in hints, with the side arrows as `<` and `>` to save room
+    fn hint_name(&self) -> String {
+        match (self.code, self.modifiers) {
+            (KeyCode::Left, KeyModifiers::NONE) => "<".to_string(),
+            (KeyCode::Right, KeyModifiers::NONE) => ">".to_string(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (modifier, name) in [(KeyModifiers::CONTROL, "ctrl+"), (KeyModifiers::ALT, "alt+"), (KeyModifiers::SHIFT, "shift+")] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}", name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            code => write!(f, "{}", format!("{:?}", code).to_lowercase()),
+        }
+    }
+}
+
+/// Which action every bound key stands for
+pub struct Keymap {
+    bindings: Vec<(Key, Action)>,
+}
+
+impl Keymap {
+    /// Arrows to move around, mnemonic letters for the rest
+    pub fn default_preset() -> Self {
+        let bindings = [
+            ("up", Action::Up), ("down", Action::Down), ("left", Action::Left), ("right", Action::Right),
+            ("enter", Action::Confirm), ("esc", Action::Back),
+            ("space", Action::PauseResume), ("s", Action::Stop), ("n", Action::Skip), ("t", Action::ToggleTime),
+            ("'", Action::InternalInterruption), ("-", Action::ExternalInterruption), ("i", Action::Capture), ("r", Action::Review),
+            ("q", Action::Quit), ("ctrl+c", Action::Quit), ("T", Action::Tasks), ("S", Action::Stats), ("?", Action::Help),
+        ];
+        Self { bindings: bindings.into_iter().map(|(key, action)| (Key::parse(key).unwrap(), action)).collect() }
+    }
+
+    /// The default keys, plus `hjkl` to move around
+    pub fn vim_preset() -> Self {
+        let mut res = Self::default_preset();
+        for (key, action) in [("k", Action::Up), ("j", Action::Down), ("h", Action::Left), ("l", Action::Right)] {
+            res.bindings.push((Key::parse(key).unwrap(), action));
+        }
+        res
+    }
+
+    pub fn preset(name: &str) -> Result<Self> {
+        match name {
+            "default" => Ok(Self::default_preset()),
+            "vim" => Ok(Self::vim_preset()),
+            _ => Err(KeymapError::UnknownPreset(name.to_string()))?,
+        }
+    }
+
+    /// The preset of the config, with the keys of the actions it overrides replaced
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut res = Self::preset(&config.keymap)?;
+        for (name, keys) in &config.keys {
+            let action = Action::parse(name)?;
+            let keys = keys.iter().map(|x| Key::parse(x)).collect::<Result<Vec<_>>>()?;
+            // A key does one thing, so it's taken away from whatever it did before
+            res.bindings.retain(|(key, x)| *x != action && !keys.contains(key));
+            res.bindings.extend(keys.into_iter().map(|key| (key, action)));
+        }
+        Ok(res)
+    }
+
+    pub fn action(&self, key: KeyEvent) -> Option<Action> {
+        let key = Key::from(key);
+        self.bindings.iter().find(|(x, _)| *x == key).map(|(_, action)| *action)
+    }
+
+    /// Every key bound to an action
+    pub fn keys(&self, action: Action) -> Vec<Key> {
+        self.bindings.iter().filter(|(_, x)| *x == action).map(|(key, _)| *key).collect()
+    }
+
+    /// A hint like `esc:back` naming the first key of each action, or `None` if one has no key to name.<br>
+    /// While typing only shortcuts reach the actions, so it names one of those, or else the key the text box takes as it is.
+    pub fn hint(&self, actions: &[Action], label: &str, typing: bool) -> Option<String> {
+        let keys = actions.iter().map(|action| {
+            let keys = self.keys(*action);
+            let key = match typing {
+                true => keys.into_iter().find(Key::is_shortcut).or_else(|| Key::typed(*action)),
+                false => keys.first().copied(),
+            };
+            key.map(|x| x.hint_name())
+        }).collect::<Option<Vec<_>>>()?;
+        Some(format!("{}:{}", keys.join("/"), label))
+    }
+
+    /// Turns a key into input for a screen.<br>
+    /// Keys go through the keymap only for the actions the screen takes, or that work everywhere,
+    /// so the letters screens use for their own commands keep working.
+    /// While typing only keys held with ctrl or alt do.
+    pub fn input(&self, key: KeyEvent, actions: &[Action], typing: bool) -> UserInput {
+        let action = self.action(key).filter(|x| Action::NAVIGATION.contains(x) || Action::GLOBAL.contains(x) || actions.contains(x));
+        let shortcut = Key::from(key).is_shortcut();
+        match action {
+            Some(action) if !typing || shortcut => UserInput::from(action),
+            _ => UserInput::from(key),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key { code, modifiers }
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert!(Key::parse("q").unwrap() == key(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert!(Key::parse("space").unwrap() == key(KeyCode::Char(' '), KeyModifiers::NONE));
+        assert!(Key::parse("ctrl+c").unwrap() == key(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert!(Key::parse("Ctrl+Alt+Enter").unwrap() == key(KeyCode::Enter, KeyModifiers::CONTROL | KeyModifiers::ALT));
+        assert!(Key::parse("T").unwrap() == key(KeyCode::Char('T'), KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn parses_plus_as_a_key() {
+        assert!(Key::parse("+").unwrap() == key(KeyCode::Char('+'), KeyModifiers::NONE));
+        assert!(Key::parse("++").unwrap() == key(KeyCode::Char('+'), KeyModifiers::NONE));
+        assert!(Key::parse("ctrl++").unwrap() == key(KeyCode::Char('+'), KeyModifiers::CONTROL));
+    }
+
+    #[test]
+    fn matches_shifted_keys_as_terminals_send_them() {
+        let shifted = |code| Key::from(KeyEvent::new(code, KeyModifiers::SHIFT));
+        assert!(Key::parse("shift+a").unwrap() == shifted(KeyCode::Char('A')));
+        assert!(Key::parse("A").unwrap() == shifted(KeyCode::Char('A')));
+        assert!(Key::parse("shift+tab").unwrap() == Key::from(KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT)));
+    }
+
+    #[test]
+    fn parses_function_keys() {
+        assert!(Key::parse("f5").unwrap() == key(KeyCode::F(5), KeyModifiers::NONE));
+        assert!(Key::parse("F12").unwrap() == key(KeyCode::F(12), KeyModifiers::NONE));
+        // A lone `f` is the letter
+        assert!(Key::parse("f").unwrap() == key(KeyCode::Char('f'), KeyModifiers::NONE));
+        assert!(Key::parse("fx").is_err());
+    }
+
+    #[test]
+    fn refuses_invalid_keys() {
+        for text in ["", "hyper+x", "ctrl+", "qq", "ctrl+nope"] {
+            assert!(Key::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn shows_keys_as_they_parse() {
+        for text in ["q", "space", "ctrl+c", "shift+tab", "f5", "ctrl++"] {
+            assert_eq!(Key::parse(text).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn hints_name_the_keys_in_use() {
+        let mut config = Config::default();
+        config.keys.insert("review".to_string(), vec!["v".to_string()]);
+        config.keys.insert("confirm".to_string(), vec!["o".to_string(), "ctrl+s".to_string()]);
+        config.keys.insert("skip".to_string(), Vec::new());
+        let keymap = Keymap::from_config(&config).unwrap();
+
+        assert_eq!(keymap.hint(&[Action::Review], "review", false).unwrap(), "v:review");
+        assert_eq!(keymap.hint(&[Action::Left, Action::Right], "range", false).unwrap(), "</>:range");
+        assert_eq!(keymap.hint(&[Action::Confirm], "save", false).unwrap(), "o:save");
+        assert!(keymap.hint(&[Action::Skip], "skip", false).is_none());
+        // While typing only shortcuts reach the actions, the text box taking the rest
+        assert_eq!(keymap.hint(&[Action::Confirm], "save", true).unwrap(), "ctrl+s:save");
+        assert_eq!(keymap.hint(&[Action::Back], "skip", true).unwrap(), "esc:skip");
+    }
+}
@@ -1,40 +1,114 @@
+use std::{rc::Rc, sync::mpsc::Sender};
+
 use anyhow::Result;
-use overfocus::{pomodoro::{PomodoroHandle, Pomodoro, PomodoroStage}, unwrap_err};
-use tui::{backend::Backend, text::{Spans, Span}, widgets::{Block, Borders, Paragraph}, layout::Alignment};
+use overfocus::{config::Config, pomodoro::{PomodoroHandle, Pomodoro, PomodoroStage, PomodoroOptions, PomodoroSnapshot, SET_LENGTH}, history::InterruptionKind, inbox::Inbox, unwrap_err};
+use tui::{backend::Backend, text::{Spans, Span}, widgets::{Block, Borders, Paragraph, Gauge}, layout::{Alignment, Rect}, style::Modifier};
+
+use crate::app::{ui::{UI, UIContext}, utils::{sub_rect, clip}, input::{UserInput, Target}, events::AppEvent, keymap::{Action, Keymap}, theme::{border_style, regular_style, highlight_style, warn_log_style, err_log_style, paused_style, stage_style}, widgets::{text_input::{TextInput, TextInputEvent}, big_clock::BigClock}};
+
+const WIDTH: u16 = 20;
+const HEIGHT: u16 = 12;
+
+/// Stages of a set in the order they run, as shown by the cycle markers
+const CYCLE: [PomodoroStage; 2 * SET_LENGTH as usize] = [
+    PomodoroStage::Work, PomodoroStage::ShortBreak,
+    PomodoroStage::Work, PomodoroStage::ShortBreak,
+    PomodoroStage::Work, PomodoroStage::LongBreak,
+];
 
-use crate::app::{ui::{UI, UIContext}, utils::sub_rect, input::{UserInput, Target}, styles::{regular_style, highlight_style}};
+/// What a text prompt opened over the clock is asking for
+enum Prompt {
+    Interruption(InterruptionKind),
+    Capture,
+}
 
 pub struct PomodoroClockUI {
     clock: PomodoroHandle,
     selected: u8,
     paused: bool,
+    prompt: Option<(Prompt, TextInput)>,
+    inbox_len: usize,
+    reflect: bool,
+    /// Shows the time left in the stage instead of the time spent
+    remaining: bool,
+    keymap: Rc<Keymap>,
 }
 
 impl<B: Backend> UI<B> for PomodoroClockUI {
     fn ui(&mut self, frame: &mut tui::Frame<B>, rect: tui::layout::Rect, input: &mut UserInput) {
         // Handle Events
+        self.check_finished_work(input);
+        self.handle_prompt_input(input);
+        input.consume_matches(|x| matches!(x, UserInput::Action(Action::Review)), |input| {
+            // The inbox is reviewed during breaks so it doesn't break the focus
+            if !self.is_working() {
+                *input = UserInput::Goto(Target::Inbox);
+            }
+        });
+        input.consume_matches(|x| matches!(x, UserInput::Action(Action::ToggleTime)), |_| self.remaining = !self.remaining);
         input.consume_matches(|x| matches!(x, UserInput::Up), |_| if self.selected == 1 { self.selected = 0 });
         input.consume_matches(|x| matches!(x, UserInput::Down), |_| if self.selected == 0 { self.selected = 1 });
+        input.consume_matches(|x| matches!(x, UserInput::Action(Action::Skip)), |_| unwrap_err!(Pomodoro::skip(&self.clock)));
+        input.consume_matches(|x| matches!(x, UserInput::Action(Action::PauseResume)), |_| self.toggle_pause());
+        input.consume_matches(|x| matches!(x, UserInput::Action(Action::Stop)), |input| {
+            unwrap_err!(Pomodoro::stop(&self.clock));
+            *input = UserInput::Goto(Target::PopStack);
+        });
         if input.consume_matches(|x| matches!(x, UserInput::Enter), |input| {
             if self.selected == 1 {
                 *input = UserInput::Goto(Target::PopStack);
-                Pomodoro::stop(&self.clock).unwrap()
+                unwrap_err!(Pomodoro::stop(&self.clock))
             } else {
-                if self.paused {
-                    Pomodoro::resume(&self.clock).unwrap()
-                } else {
-                    Pomodoro::pause(&self.clock).unwrap()
-                }
-                self.paused = !self.paused;
+                self.toggle_pause();
             }
         }).is_some() { return }
         
         // Display things
-        let rect = sub_rect(rect, (20, 8));
+        let rect = self.draw_big_clock(frame, rect);
 
-        let block = Block::default().borders(Borders::ALL).title(" [ Pomodoro ] ").title_alignment(Alignment::Center).style(regular_style());
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ Pomodoro ] ").title_alignment(Alignment::Center).style(regular_style());
+        let inner = block.inner(rect);
         let paragraph = Paragraph::new(self.get_spans()).block(block).style(regular_style());
         frame.render_widget(paragraph, rect);
+        self.draw_stage_gauge(frame, clip(Rect { y: inner.y + 5, height: 1, ..inner }, inner));
+
+        if let Some((_, text_input)) = &self.prompt {
+            text_input.render(frame, sub_rect(rect, (30, 3)));
+        }
+    }
+
+    /// Quitting stops the clock first, so the stage gets recorded
+    fn on_quit(&mut self) {
+        unwrap_err!(Pomodoro::stop(&self.clock));
+    }
+
+    fn handle_context(&mut self, ctx: UIContext) {
+        match ctx {
+            UIContext::Keymap(keymap) => self.keymap = keymap,
+            // Coming back from reviewing the inbox
+            _ => self.reload_inbox_len(),
+        }
+    }
+
+    fn actions(&self) -> Vec<Action> {
+        vec![Action::PauseResume, Action::Stop, Action::Skip, Action::ToggleTime, Action::InternalInterruption, Action::ExternalInterruption, Action::Capture, Action::Review]
+    }
+
+    fn typing(&self) -> bool {
+        self.prompt.is_some()
+    }
+
+    fn compact(&self) -> Option<Spans<'static>> {
+        let (secs, ratio, reps, stage, voided) = unwrap_err!(Pomodoro::lock_and(&self.clock, |x| {
+            (self.displayed_secs(&x), x.seconds() as f64 / x.stage().length_secs() as f64, x.repetitions(), *x.stage(), x.voided().is_some())
+        }).map(Some), else => None)?;
+        // What the clock is up to goes first, the end of the line being the first to go on narrow terminals
+        let (state, style) = if voided { (" voided", err_log_style()) } else if self.paused { (" paused", paused_style()) } else { ("", stage_style(stage)) };
+
+        Some(Spans::from(vec![
+            Span::styled(format!("{:02}:{:02}{}", secs / 60, secs % 60, state), style),
+            Span::raw(format!(" {} {}%", Self::display_stage(stage, reps), (ratio.clamp(0.0, 1.0) * 100.0) as u8)),
+        ]))
     }
 
     fn get_context(&self) -> Option<UIContext> {
@@ -44,43 +118,204 @@ impl<B: Backend> UI<B> for PomodoroClockUI {
 }
 
 impl PomodoroClockUI {
-    pub fn new() -> Self {
-        let clock = Pomodoro::start();
-        Self { clock, selected: 0, paused: false }
+    /// Starts a clock, which redraws the app through `events` as it goes
+    pub fn new(options: PomodoroOptions, events: Sender<AppEvent>) -> Self {
+        Self::with_clock(Pomodoro::start(options), false, events)
+    }
+
+    /// Brings back the clock that was running when the app crashed
+    pub fn restore(snapshot: PomodoroSnapshot, events: Sender<AppEvent>) -> Self {
+        let paused = snapshot.paused;
+        Self::with_clock(Pomodoro::restore(snapshot), paused, events)
+    }
+
+    fn with_clock(clock: PomodoroHandle, paused: bool, events: Sender<AppEvent>) -> Self {
+        unwrap_err!(Pomodoro::on_change(&clock, move || _ = events.send(AppEvent::Timer)));
+        let reflect = unwrap_err!(Config::load(), else => Config::default()).reflect;
+        let mut res = Self { clock, selected: 0, paused, prompt: None, inbox_len: 0, reflect, remaining: true, keymap: Rc::new(Keymap::default_preset()) };
+        res.reload_inbox_len();
+        res
+    }
+
+    fn toggle_pause(&mut self) {
+        if self.paused {
+            unwrap_err!(Pomodoro::resume(&self.clock))
+        } else {
+            unwrap_err!(Pomodoro::pause(&self.clock))
+        }
+        self.paused = !self.paused;
+    }
+
+    /// Opens the reflection screen once a work stage ends
+    fn check_finished_work(&mut self, input: &mut UserInput) {
+        // Waits for a frame without input so no key gets lost
+        if *input != UserInput::None {
+            return;
+        }
+
+        let finished = unwrap_err!(Pomodoro::lock_and(&self.clock, |mut x| x.take_finished_work()), else => None);
+        if let (Some(session), true) = (finished, self.reflect) {
+            *input = UserInput::Goto(Target::Reflection(session));
+        }
+    }
+
+    /// Opens the interruption note prompt or the inbox one when their actions come,
+    /// acting on their text once it's submitted
+    fn handle_prompt_input(&mut self, input: &mut UserInput) {
+        if let Some((prompt, text_input)) = &mut self.prompt {
+            match (text_input.handle_input(input), prompt) {
+                (Some(TextInputEvent::Submitted(note)), Prompt::Interruption(kind)) => {
+                    let note = if note.is_empty() { None } else { Some(note) };
+                    unwrap_err!(Pomodoro::interrupt(&self.clock, *kind, note));
+                    self.prompt = None;
+                },
+                (Some(TextInputEvent::Submitted(text)), Prompt::Capture) => {
+                    if !text.is_empty() {
+                        unwrap_err!(Inbox::capture(text));
+                        self.inbox_len += 1;
+                    }
+                    self.prompt = None;
+                },
+                (Some(TextInputEvent::Cancelled), _) => self.prompt = None,
+                (None, _) => {},
+            }
+            return;
+        }
+
+        let prompt = match input {
+            UserInput::Action(Action::InternalInterruption) => Prompt::Interruption(InterruptionKind::Internal),
+            UserInput::Action(Action::ExternalInterruption) => Prompt::Interruption(InterruptionKind::External),
+            UserInput::Action(Action::Capture) => Prompt::Capture,
+            _ => return,
+        };
+
+        let title = match prompt {
+            Prompt::Interruption(_) if !self.is_working() => return,
+            Prompt::Interruption(kind) => format!("{} interruption note", Self::display_kind(kind)),
+            Prompt::Capture => "Capture to inbox".to_string(),
+        };
+
+        *input = UserInput::Consumed;
+        self.prompt = Some((prompt, TextInput::new(title)));
+    }
+
+    /// Draws the time as big as it fits above the clock box, returning where the box goes.<br>
+    /// Without room for it, the box is centered and shows the time on its own.
+    fn draw_big_clock<B: Backend>(&self, frame: &mut tui::Frame<B>, rect: Rect) -> Rect {
+        let (secs, stage, voided) = unwrap_err!(Pomodoro::lock_and(&self.clock, |x| (self.displayed_secs(&x), *x.stage(), x.voided().is_some())), else => return sub_rect(rect, (WIDTH, HEIGHT)));
+        let style = if voided { err_log_style() } else if self.paused { paused_style() } else { stage_style(stage) };
+        let clock = BigClock::new(format!("{:02}:{:02}", secs / 60, secs % 60), style);
+
+        let Some(scale) = clock.fit(rect.width.saturating_sub(2), rect.height.saturating_sub(HEIGHT + 1)) else {
+            return sub_rect(rect, (WIDTH, HEIGHT));
+        };
+        let (width, height) = clock.size(scale);
+        let top = rect.y + (rect.height - height - 1 - HEIGHT) / 2;
+        clock.render(frame, Rect::new(rect.x + (rect.width - width) / 2, top, width, height), scale);
+        Rect::new(rect.x + (rect.width - WIDTH) / 2, top + height + 1, WIDTH, HEIGHT)
+    }
+
+    /// Fills up as the stage goes, turning to the warning style in its last fifth and the error one in its last minute
+    fn draw_stage_gauge<B: Backend>(&self, frame: &mut tui::Frame<B>, rect: Rect) {
+        let Some((secs, remaining, stage)) = unwrap_err!(Pomodoro::lock_and(&self.clock, |x| (x.seconds(), x.remaining_secs(), *x.stage())).map(Some), else => None) else { return };
+        let ratio = (secs as f64 / stage.length_secs() as f64).clamp(0.0, 1.0);
+        let style = if remaining <= 60 { err_log_style() } else if ratio >= 0.8 { warn_log_style() } else { stage_style(stage) };
+
+        let gauge = Gauge::default().ratio(ratio).label(format!("{}%", (ratio * 100.0) as u8)).gauge_style(style);
+        frame.render_widget(gauge, rect);
+    }
+
+    /// A marker per stage of the set, the finished ones in their stage's style and the current one reversed
+    fn cycle_spans(stage: PomodoroStage, reps: u8) -> Spans<'static> {
+        let current = match stage {
+            PomodoroStage::Work => 2 * reps as usize,
+            PomodoroStage::ShortBreak => 2 * reps as usize + 1,
+            PomodoroStage::LongBreak => CYCLE.len() - 1,
+        };
+
+        Spans::from(CYCLE.iter().enumerate().map(|(i, stage)| {
+            let letter = match stage {
+                PomodoroStage::Work => "W",
+                PomodoroStage::ShortBreak => "b",
+                PomodoroStage::LongBreak => "L",
+            };
+            let style = if i < current { stage_style(*stage) } else if i == current { stage_style(*stage).add_modifier(Modifier::REVERSED) } else { regular_style().add_modifier(Modifier::DIM) };
+            Span::styled(format!("[{}]", letter), style)
+        }).collect::<Vec<_>>())
+    }
+
+    /// The seconds shown by the clock, spent or left depending on the toggle
+    fn displayed_secs(&self, clock: &Pomodoro) -> usize {
+        if self.remaining { clock.remaining_secs() } else { clock.seconds() }
     }
 
-    fn get_spans(&self) -> Vec<Spans> {
-        let (secs, reps, pomos, stage) = Pomodoro::lock_and(&self.clock, |x| (x.seconds(), x.repetitions(), x.pomodoros(), *x.stage())).unwrap();
+    fn is_working(&self) -> bool {
+        unwrap_err!(Pomodoro::lock_and(&self.clock, |x| *x.stage() == PomodoroStage::Work), else => true)
+    }
+
+    fn reload_inbox_len(&mut self) {
+        self.inbox_len = unwrap_err!(Inbox::load(), else => Inbox::default()).entries.len();
+    }
+
+    fn get_spans(&self) -> Vec<Spans<'_>> {
+        let clock = Pomodoro::lock_and(&self.clock, |x| {
+            let internal = x.interruptions().iter().filter(|i| i.kind == InterruptionKind::Internal).count();
+            (self.displayed_secs(&x), x.repetitions(), x.pomodoros(), *x.stage(), x.voided(), internal, x.interruptions().len() - internal)
+        });
+        let Some((secs, reps, pomos, stage, voided, internal, external)) = unwrap_err!(clock.map(Some), else => None) else {
+            return vec![Spans::from(Span::styled("The clock stopped working.", err_log_style()))];
+        };
         
         let mut res = vec![
             Spans::from(format!("Pomodoros: {}", pomos)),
             Spans::from(format!("Stage: {}", Self::display_stage(stage, reps))),
-            Spans::from(format!("Elapsed: ({}:{:02})", secs / 60, secs % 60)),
+            match voided {
+                Some(_) => Spans::from(Span::styled("VOIDED", err_log_style())),
+                None => Spans::from(format!("{}: ({}:{:02})", if self.remaining { "Left" } else { "Elapsed" }, secs / 60, secs % 60)),
+            },
+            Spans::from(format!("Interrupts: '{} -{}", internal, external)),
+            match stage {
+                PomodoroStage::Work => Spans::from(format!("Inbox: {}", self.inbox_len)),
+                _ => match self.keymap.hint(&[Action::Review], "review", false) {
+                    Some(hint) => Spans::from(format!("Inbox: {} {}", self.inbox_len, hint)),
+                    None => Spans::from(format!("Inbox: {}", self.inbox_len)),
+                },
+            },
+            // Left for the stage gauge
+            Spans::from(""),
+            Self::cycle_spans(stage, reps),
             Spans::from(""),
         ];
 
         if self.selected == 0 {
             res.extend([
-                Spans::from(Span::styled(format!(">{}", self.get_pause_text()), highlight_style())),
+                Spans::from(Span::styled(format!(">{}", self.get_pause_text(voided.is_some())), highlight_style())),
                 Spans::from(Span::styled("-Stop and exit", regular_style())),
             ]);
         } else {
             res.extend([
-                Spans::from(Span::styled(format!("-{}", self.get_pause_text()), regular_style())),
+                Spans::from(Span::styled(format!("-{}", self.get_pause_text(voided.is_some())), regular_style())),
                 Spans::from(Span::styled(">Stop and exit", highlight_style())),
             ]);
         }
         res
     }
 
-    fn get_pause_text(&self) -> &str {
-        if self.paused { "Resume" } else { "Pause" }
+    fn get_pause_text(&self, voided: bool) -> &str {
+        if voided { "Restart" } else if self.paused { "Resume" } else { "Pause" }
+    }
+
+    fn display_kind(kind: InterruptionKind) -> &'static str {
+        match kind {
+            InterruptionKind::Internal => "Internal",
+            InterruptionKind::External => "External",
+        }
     }
 
     fn display_stage(stage: PomodoroStage, reps: u8) -> String {
         match stage {
-            PomodoroStage::Work => format!("Work ({}/3)", reps + 1),
-            PomodoroStage::ShortBreak => format!("Break ({}/3)", reps + 1),
+            PomodoroStage::Work => format!("Work ({}/{})", reps + 1, SET_LENGTH),
+            PomodoroStage::ShortBreak => format!("Break ({}/{})", reps + 1, SET_LENGTH),
             PomodoroStage::LongBreak => "Long Break".to_string(),
         }
     }
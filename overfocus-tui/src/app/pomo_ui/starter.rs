@@ -1,72 +1,257 @@
-use tui::{backend::Backend, layout::{Rect, Alignment}, text::{Span, Spans}, widgets::{Block, Borders, Paragraph}};
+use chrono::{NaiveDate, Datelike};
+use overfocus::{config::Config, days, history::History, stats::{self, DateRange}, pomodoro::PomodoroOptions, tasks::TaskList, tags::{parse_tags, display_tags}, unwrap_err};
+use tui::{backend::Backend, layout::{Rect, Alignment}, text::Spans, widgets::{Block, Borders, Paragraph, Gauge}};
+use uuid::Uuid;
 
-use crate::app::{utils::sub_rect, input::{UserInput, Target}, ui::{UI, UIContext}, styles::{regular_style, highlight_style}};
+use crate::{cli::RunArgs, app::{utils::{sub_rect, handle_menu_input, menu_spans, truncate}, input::{UserInput, Target}, ui::{UI, UIContext}, theme::{border_style, regular_style, highlight_style}, widgets::text_input::{TextInput, TextInputEvent}}};
 
 struct Stats { max: u8, cur: u8, avg: u8 }
 
+#[derive(Clone, Copy)]
+enum MenuEntry { Start, Task, Project, Tags, Profile, Goal, Tasks, Inbox, Strict, Stats, History, Report, Achievements, Export, Exit }
+
+const MENU: [MenuEntry; 15] = [
+    MenuEntry::Start, MenuEntry::Task, MenuEntry::Project, MenuEntry::Tags, MenuEntry::Profile, MenuEntry::Goal,
+    MenuEntry::Tasks, MenuEntry::Inbox, MenuEntry::Strict, MenuEntry::Stats, MenuEntry::History, MenuEntry::Report, MenuEntry::Achievements, MenuEntry::Export, MenuEntry::Exit,
+];
+const WIDTH: u16 = 30;
+
 /// The struct that holds the information of the pomodoro starting screen
 pub struct PomodoroStarterUI {
     stats: Stats,
-    selected: u8,
+    config: Config,
+    tasks: TaskList,
+    task: Option<Uuid>,
+    project: Option<String>,
+    tags: Vec<String>,
+    /// Pomodoros completed today, towards the daily goal
+    done_today: usize,
+    selected: usize,
+    /// Text box editing the project, the tags or the profile
+    prompt: Option<(MenuEntry, TextInput)>,
 }
 
 impl<B: Backend> UI<B> for PomodoroStarterUI {
     fn ui(&mut self, frame: &mut tui::Frame<B>, rect: Rect, input: &mut UserInput) {
         // Handle input
-        input.consume_matches(|x| matches!(x, UserInput::Up), |_| if self.selected == 1 { self.selected = 0 });
-        input.consume_matches(|x| matches!(x, UserInput::Down), |_| if self.selected == 0 { self.selected = 1 });
+        self.handle_prompt_input(input);
+        handle_menu_input(input, &mut self.selected, MENU.len());
+        match MENU[self.selected] {
+            MenuEntry::Task => {
+                input.consume_matches(|x| matches!(x, UserInput::Left), |_| self.cycle_task(false));
+                input.consume_matches(|x| matches!(x, UserInput::Right), |_| self.cycle_task(true));
+            },
+            MenuEntry::Profile => {
+                input.consume_matches(|x| matches!(x, UserInput::Left), |_| self.cycle_profile(false));
+                input.consume_matches(|x| matches!(x, UserInput::Right), |_| self.cycle_profile(true));
+            },
+            MenuEntry::Goal => {
+                input.consume_matches(|x| matches!(x, UserInput::Left), |_| self.change_goal(false));
+                input.consume_matches(|x| matches!(x, UserInput::Right), |_| self.change_goal(true));
+            },
+            _ => {},
+        }
         if input.consume_matches(|x| matches!(x, UserInput::Enter), |input| {
             // Converts input to redirections
-            *input = UserInput::Goto(if self.selected == 0 { Target::Pomodoro } else { Target::Quit })
+            match MENU[self.selected] {
+                MenuEntry::Start => *input = UserInput::Goto(Target::Pomodoro(self.options())),
+                MenuEntry::Task => self.cycle_task(true),
+                MenuEntry::Project => {
+                    let project = self.project.clone().unwrap_or_default();
+                    self.prompt = Some((MenuEntry::Project, TextInput::new("Project").with_value(project)));
+                },
+                MenuEntry::Tags => self.prompt = Some((MenuEntry::Tags, TextInput::new("Tags").with_value(display_tags(&self.tags)))),
+                MenuEntry::Profile => self.prompt = Some((MenuEntry::Profile, TextInput::new("Profile").with_value(self.config.profile.clone()))),
+                MenuEntry::Goal => self.change_goal(true),
+                MenuEntry::Tasks => *input = UserInput::Goto(Target::Tasks),
+                MenuEntry::Inbox => *input = UserInput::Goto(Target::Inbox),
+                MenuEntry::Strict => self.toggle_strict(),
+                MenuEntry::Stats => *input = UserInput::Goto(Target::Stats),
+                MenuEntry::History => *input = UserInput::Goto(Target::History),
+                MenuEntry::Report => *input = UserInput::Goto(Target::Report),
+                MenuEntry::Achievements => *input = UserInput::Goto(Target::Achievements),
+                MenuEntry::Export => *input = UserInput::Goto(Target::Export),
+                MenuEntry::Exit => *input = UserInput::Goto(Target::Quit),
+            }
         }).is_some() { return }
 
         // Create layout
-        let rect = sub_rect(rect, (20, 8));
+        let rect = sub_rect(rect, (WIDTH, 22));
 
         // Actually do shit
         let text = self.get_spans();
 
-        let block = Block::default().borders(Borders::ALL).title(" [ Pomodoro ] ").title_alignment(Alignment::Center).style(regular_style());
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ Pomodoro ] ").title_alignment(Alignment::Center).style(regular_style());
+        let inner = block.inner(rect);
         let paragraph = Paragraph::new(text).block(block).style(regular_style());
         frame.render_widget(paragraph, rect);
+
+        // The goal bar goes on the empty line left under the stats
+        let goal = self.goal_today();
+        if goal > 0 && inner.height > 3 {
+            let ratio = (self.done_today as f64 / goal as f64).min(1.0);
+            let gauge = Gauge::default().ratio(ratio).label(format!("{}/{} today", self.done_today, goal)).gauge_style(highlight_style());
+            frame.render_widget(gauge, Rect { y: inner.y + 3, height: 1, ..inner });
+        }
+
+        if let Some((_, text_input)) = &self.prompt {
+            text_input.render(frame, sub_rect(rect, (WIDTH + 6, 3)));
+        }
     }
 
     fn handle_context(&mut self, ctx: UIContext) {
         // Updates pomodoro count when done
-        #[allow(irrefutable_let_patterns)]
         if let UIContext::PomodoroClock { pomodoros } = ctx {
             self.stats.cur += pomodoros;
             if self.stats.max < self.stats.cur {
                 self.stats.max = self.stats.cur;
             }
+            self.done_today = Self::load_done_today();
         }
+
+        // Both the clock and the tasks screen can change the task list
+        self.reload_tasks();
+    }
+
+    fn typing(&self) -> bool {
+        self.prompt.is_some()
     }
 }
 
 impl PomodoroStarterUI {
-    pub fn new() -> Self {
-        Self { stats: Stats { max: 0, cur: 0, avg: 0 }, selected: 0 }
+    pub fn new(args: RunArgs) -> Self {
+        let mut config = unwrap_err!(Config::load(), else => Config::default());
+        if let Some(profile) = args.profile {
+            config.profile = profile;
+            unwrap_err!(config.save());
+        }
+
+        let tasks = unwrap_err!(TaskList::load(), else => TaskList::default());
+        Self {
+            stats: Stats { max: 0, cur: 0, avg: 0 }, config, tasks, task: None,
+            project: args.project, tags: args.tags, done_today: Self::load_done_today(), selected: 0, prompt: None,
+        }
     }
-    
-    fn get_spans(&self) -> Vec<Spans> {
+
+    fn options(&self) -> PomodoroOptions {
+        PomodoroOptions { strict: self.config.strict, task: self.task, project: self.project.clone(), tags: self.tags.clone() }
+    }
+
+    fn handle_prompt_input(&mut self, input: &mut UserInput) {
+        let Some((entry, text_input)) = &mut self.prompt else { return };
+        match (text_input.handle_input(input), entry) {
+            (Some(TextInputEvent::Submitted(project)), MenuEntry::Project) => {
+                self.project = if project.is_empty() { None } else { Some(project) };
+                self.prompt = None;
+            },
+            (Some(TextInputEvent::Submitted(profile)), MenuEntry::Profile) => {
+                if !profile.is_empty() {
+                    self.config.profile = profile;
+                    unwrap_err!(self.config.save());
+                }
+                self.prompt = None;
+            },
+            (Some(TextInputEvent::Submitted(tags)), _) => {
+                self.tags = parse_tags(&tags);
+                self.prompt = None;
+            },
+            (Some(TextInputEvent::Cancelled), _) => self.prompt = None,
+            (None, _) => {},
+        }
+    }
+
+    fn get_spans(&self) -> Vec<Spans<'_>> {
         let mut res = vec![
             Spans::from(format!("Max: {}", self.stats.max)),
             Spans::from(format!("Cur: {}", self.stats.cur)),
             Spans::from(format!("Avg: {}", self.stats.avg)),
+            Spans::from(if self.goal_today() == 0 { "No goal today" } else { "" }),
             Spans::from(""),
         ];
 
-        if self.selected == 0 {
-            res.extend([
-                Spans::from(Span::styled(">Start", highlight_style())),
-                Spans::from(Span::styled("-Exit", regular_style())),
-            ]);
-        } else {
-            res.extend([
-                Spans::from(Span::styled("-Start", regular_style())),
-                Spans::from(Span::styled(">Exit", highlight_style())),
-            ]);
-        }
+        res.extend(menu_spans(MENU.iter().map(|x| self.display_entry(*x)), self.selected));
         res
     }
-}
\ No newline at end of file
+
+    fn display_entry(&self, entry: MenuEntry) -> String {
+        match entry {
+            MenuEntry::Start => "Start".to_string(),
+            MenuEntry::Task => {
+                let title = self.task.and_then(|id| self.tasks.get(id)).map(|x| x.title.as_str()).unwrap_or("None");
+                format!("Task: <{}>", truncate(title, WIDTH as usize - 12))
+            },
+            MenuEntry::Project => format!("Project: {}", truncate(self.project.as_deref().unwrap_or("None"), WIDTH as usize - 12)),
+            MenuEntry::Tags => {
+                let tags = if self.tags.is_empty() { "None".to_string() } else { display_tags(&self.tags) };
+                format!("Tags: {}", truncate(&tags, WIDTH as usize - 9))
+            },
+            MenuEntry::Profile => format!("Profile: <{}>", truncate(&self.config.profile, WIDTH as usize - 14)),
+            MenuEntry::Goal => match self.goal_today() {
+                0 => "Today's goal: <Rest>".to_string(),
+                goal => format!("Today's goal: <{}>", goal),
+            },
+            MenuEntry::Tasks => "Tasks".to_string(),
+            MenuEntry::Inbox => "Inbox".to_string(),
+            MenuEntry::Strict => format!("Strict: {}", if self.config.strict.enabled { "On" } else { "Off" }),
+            MenuEntry::Stats => "Stats".to_string(),
+            MenuEntry::History => "History".to_string(),
+            MenuEntry::Report => "Report".to_string(),
+            MenuEntry::Achievements => "Achievements".to_string(),
+            MenuEntry::Export => "Export".to_string(),
+            MenuEntry::Exit => "Exit".to_string(),
+        }
+    }
+
+    /// Selects the next or previous open task, going through no task at all
+    fn cycle_task(&mut self, forward: bool) {
+        let mut options: Vec<Option<Uuid>> = vec![None];
+        options.extend(self.tasks.open().map(|x| Some(x.id)));
+
+        let current = options.iter().position(|x| *x == self.task).unwrap_or(0);
+        let next = if forward { (current + 1) % options.len() } else { (current + options.len() - 1) % options.len() };
+        self.task = options[next];
+    }
+
+    /// Switches to the next or previous profile
+    fn cycle_profile(&mut self, forward: bool) {
+        let profiles = self.config.profiles();
+        let current = profiles.iter().position(|x| *x == self.config.profile).unwrap_or(0);
+        let next = if forward { (current + 1) % profiles.len() } else { (current + profiles.len() - 1) % profiles.len() };
+        self.config.profile = profiles[next].clone();
+        unwrap_err!(self.config.save());
+    }
+
+    /// Raises or lowers the goal of today's weekday for the active profile
+    fn change_goal(&mut self, up: bool) {
+        let goal = &mut self.config.goals_mut().weekdays[Self::today().weekday().num_days_from_monday() as usize];
+        *goal = if up { goal.saturating_add(1) } else { goal.saturating_sub(1) };
+        unwrap_err!(self.config.save());
+    }
+
+    fn goal_today(&self) -> u8 {
+        self.config.goals().target(Self::today())
+    }
+
+    fn load_done_today() -> usize {
+        let today = Self::today();
+        stats::totals(&unwrap_err!(History::load(), else => History::default()), DateRange { from: today, to: today }).pomodoros
+    }
+
+    fn today() -> NaiveDate {
+        days::today()
+    }
+
+    fn reload_tasks(&mut self) {
+        self.tasks = unwrap_err!(TaskList::load(), else => TaskList::default());
+
+        // The active task may have been deleted or completed
+        if !self.tasks.open().any(|x| Some(x.id) == self.task) {
+            self.task = None;
+        }
+    }
+
+    fn toggle_strict(&mut self) {
+        self.config.strict.enabled = !self.config.strict.enabled;
+        unwrap_err!(self.config.save());
+    }
+}
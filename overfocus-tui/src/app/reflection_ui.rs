@@ -0,0 +1,73 @@
+use std::rc::Rc;
+
+use overfocus::{history::{History, Reflection}, log_info, unwrap_err};
+use tui::{backend::Backend, layout::{Rect, Alignment}, text::{Spans, Span}, widgets::{Block, Borders, Paragraph}};
+use uuid::Uuid;
+
+use crate::app::{utils::{sub_rect, clip, hint_spans}, input::{UserInput, Target}, ui::{UI, UIContext}, keymap::{Keymap, Action}, theme::{border_style, regular_style, highlight_style}, widgets::text_input::{TextInput, TextInputEvent}};
+
+const WIDTH: u16 = 44;
+
+/// Screen shown when a work stage ends, to rate the focus and note what got done.<br>
+/// The clock keeps running underneath, so the break starts on time.
+pub struct ReflectionUI {
+    session: Uuid,
+    rating: u8,
+    note: TextInput,
+    keymap: Rc<Keymap>,
+}
+
+impl<B: Backend> UI<B> for ReflectionUI {
+    fn ui(&mut self, frame: &mut tui::Frame<B>, rect: Rect, input: &mut UserInput) {
+        input.consume_matches(|x| matches!(x, UserInput::Left), |_| self.rating = (self.rating - 1).max(1));
+        input.consume_matches(|x| matches!(x, UserInput::Right), |_| self.rating = (self.rating + 1).min(5));
+        match self.note.handle_input(input) {
+            Some(TextInputEvent::Submitted(note)) => {
+                let note = if note.is_empty() { None } else { Some(note) };
+                unwrap_err!(History::reflect(self.session, Reflection { rating: self.rating, note }));
+                log_info!("Reflection saved.");
+                *input = UserInput::Goto(Target::PopStack);
+                return;
+            },
+            Some(TextInputEvent::Cancelled) => {
+                *input = UserInput::Goto(Target::PopStack);
+                return;
+            },
+            None => {},
+        }
+
+        let rect = sub_rect(rect, (WIDTH, 9));
+        let stars = format!("{}{}", "*".repeat(self.rating as usize), " ".repeat(5 - self.rating as usize));
+        let text = vec![
+            Spans::from("How focused were you?"),
+            Spans::from(vec![Span::raw("< "), Span::styled(stars, highlight_style()), Span::raw(" >")]),
+            Spans::from(""),
+            Spans::from(""),
+            Spans::from(""),
+            Spans::from(""),
+            hint_spans([self.keymap.hint(&[Action::Confirm], "save", true), self.keymap.hint(&[Action::Back], "skip", true)]),
+        ];
+
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ Work done ] ").title_alignment(Alignment::Center).style(regular_style());
+        let paragraph = Paragraph::new(text).block(block).style(regular_style()).alignment(Alignment::Center);
+        frame.render_widget(paragraph, rect);
+        self.note.render(frame, clip(Rect::new(rect.x + 1, rect.y + 4, rect.width.saturating_sub(2), 3), rect));
+    }
+
+    /// The note takes every key but the arrows
+    fn typing(&self) -> bool {
+        true
+    }
+
+    fn handle_context(&mut self, ctx: UIContext) {
+        if let UIContext::Keymap(keymap) = ctx {
+            self.keymap = keymap;
+        }
+    }
+}
+
+impl ReflectionUI {
+    pub fn new(session: Uuid) -> Self {
+        Self { session, rating: 3, note: TextInput::new("What got done?"), keymap: Rc::new(Keymap::default_preset()) }
+    }
+}
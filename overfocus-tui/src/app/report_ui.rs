@@ -0,0 +1,62 @@
+use std::rc::Rc;
+
+use overfocus::{report::{Report, ReportPeriod, ReportFormat}, unwrap_err};
+use tui::{backend::Backend, layout::{Rect, Alignment}, text::Spans, widgets::{Block, Borders, Paragraph}};
+
+use crate::app::{utils::{sub_rect, hint_spans}, input::{UserInput, Target}, ui::{UI, UIContext}, keymap::{Keymap, Action}, theme::{border_style, regular_style}};
+
+const WIDTH: u16 = 64;
+const HEIGHT: u16 = 24;
+
+/// Scrollable plain text report of the current day or week
+pub struct ReportUI {
+    period: ReportPeriod,
+    lines: Vec<String>,
+    scroll: usize,
+    keymap: Rc<Keymap>,
+}
+
+impl<B: Backend> UI<B> for ReportUI {
+    fn ui(&mut self, frame: &mut tui::Frame<B>, rect: Rect, input: &mut UserInput) {
+        let rect = sub_rect(rect, (WIDTH, HEIGHT));
+        // Borders and the help line take 3 rows
+        let visible = rect.height.saturating_sub(3) as usize;
+        let max_scroll = self.lines.len().saturating_sub(visible);
+
+        input.consume_matches(|x| matches!(x, UserInput::Up), |_| self.scroll = self.scroll.saturating_sub(1));
+        input.consume_matches(|x| matches!(x, UserInput::Down), |_| self.scroll = (self.scroll + 1).min(max_scroll));
+        input.consume_matches(|x| matches!(x, UserInput::Left | UserInput::Right), |_| {
+            let period = match self.period {
+                ReportPeriod::Day => ReportPeriod::Week,
+                ReportPeriod::Week => ReportPeriod::Day,
+            };
+            *self = Self { keymap: self.keymap.clone(), ..Self::new(period) };
+        });
+        input.consume_matches(|x| matches!(x, UserInput::Esc | UserInput::Enter), |input| *input = UserInput::Goto(Target::PopStack));
+
+        let mut text: Vec<Spans> = self.lines.iter().skip(self.scroll).take(visible).map(|x| Spans::from(x.as_str())).collect();
+        text.resize(visible, Spans::from(""));
+        text.push(hint_spans([
+            self.keymap.hint(&[Action::Up, Action::Down], "scroll", false),
+            self.keymap.hint(&[Action::Left, Action::Right], "day/week", false),
+            self.keymap.hint(&[Action::Back], "back", false),
+        ]));
+
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ Report ] ").title_alignment(Alignment::Center).style(regular_style());
+        let paragraph = Paragraph::new(text).block(block).style(regular_style());
+        frame.render_widget(paragraph, rect);
+    }
+
+    fn handle_context(&mut self, ctx: UIContext) {
+        if let UIContext::Keymap(keymap) = ctx {
+            self.keymap = keymap;
+        }
+    }
+}
+
+impl ReportUI {
+    pub fn new(period: ReportPeriod) -> Self {
+        let text = unwrap_err!(Report::current(period).map(|x| x.render(ReportFormat::Text)), else => String::new());
+        Self { period, lines: text.lines().map(String::from).collect(), scroll: 0, keymap: Rc::new(Keymap::default_preset()) }
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::Duration;
+use overfocus::{history::History, days, stats::{self, DateRange}, unwrap_err};
+use tui::{backend::Backend, layout::{Rect, Alignment, Layout, Direction, Constraint}, text::Spans, widgets::{Block, Borders, Paragraph, BarChart}};
+
+use crate::app::{utils::{sub_rect, truncate}, input::{UserInput, Target}, keymap::Action, ui::UI, theme::{border_style, regular_style, highlight_style}};
+
+/// Screen that shows statistics about the recorded history
+pub struct StatsUI {
+    per_day: Vec<(String, usize)>,
+    per_hour: Vec<(String, u64)>,
+    per_project: Vec<(String, u64, usize)>,
+}
+
+impl<B: Backend> UI<B> for StatsUI {
+    fn ui(&mut self, frame: &mut tui::Frame<B>, rect: Rect, input: &mut UserInput) {
+        if input.consume_matches(|x| matches!(x, UserInput::Enter | UserInput::Esc | UserInput::Action(Action::Stats)), |input| {
+            *input = UserInput::Goto(Target::PopStack)
+        }).is_some() { return }
+
+        let rect = sub_rect(rect, (76, 20));
+        let layout = Layout::default().direction(Direction::Vertical).constraints([
+            Constraint::Length(9),
+            Constraint::Min(0),
+        ]).split(rect);
+
+        let top = Layout::default().direction(Direction::Horizontal).constraints([
+            Constraint::Length(24),
+            Constraint::Min(0),
+        ]).split(layout[0]);
+
+        let lines: Vec<Spans> = self.per_day.iter().map(|(day, count)| Spans::from(format!("{}: {}", day, count))).collect();
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ Interruptions ] ").title_alignment(Alignment::Center).style(regular_style());
+        frame.render_widget(Paragraph::new(lines).block(block).style(regular_style()), top[0]);
+
+        let mut lines: Vec<Spans> = self.per_project.iter().map(|(project, secs, pomos)| {
+            Spans::from(format!("{:<24} {:>3}h {:02}m {:>3} pomos", truncate(project, 24), secs / 3600, (secs / 60) % 60, pomos))
+        }).collect();
+        if lines.is_empty() {
+            lines.push(Spans::from("No work this week."));
+        }
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ Projects, last 7 days ] ").title_alignment(Alignment::Center).style(regular_style());
+        frame.render_widget(Paragraph::new(lines).block(block).style(regular_style()), top[1]);
+
+        let data: Vec<(&str, u64)> = self.per_hour.iter().map(|(hour, count)| (hour.as_str(), *count)).collect();
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ Per hour ] ").title_alignment(Alignment::Center).style(regular_style());
+        let chart = BarChart::default().block(block).data(&data).bar_width(2).bar_gap(1).bar_style(highlight_style()).value_style(highlight_style());
+        frame.render_widget(chart, layout[1]);
+    }
+}
+
+impl StatsUI {
+    pub fn new() -> Self {
+        let history = unwrap_err!(History::load(), else => History::default());
+        let per_day_map = stats::interruptions_per_day(&history);
+
+        // Last week, most recent day first
+        let today = days::today();
+        let per_day = (0..7).map(|i| {
+            let day = today - Duration::days(i);
+            (day.format("%a %d/%m").to_string(), per_day_map.get(&day).copied().unwrap_or(0))
+        }).collect();
+
+        let per_hour = stats::interruptions_per_hour(&history).iter().enumerate()
+            .map(|(hour, count)| (format!("{:02}", hour), *count as u64))
+            .collect();
+
+        let per_project = stats::by_project(&history, DateRange::last_days(7)).into_iter()
+            .map(|(project, totals)| (project, totals.focus_secs, totals.pomodoros))
+            .collect();
+
+        Self { per_day, per_hour, per_project }
+    }
+}
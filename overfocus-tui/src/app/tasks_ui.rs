@@ -0,0 +1,153 @@
+use std::rc::Rc;
+
+use overfocus::{tasks::TaskList, tags::{parse_tags, display_tags}, unwrap_err};
+use tui::{backend::Backend, layout::{Rect, Alignment}, text::Spans, widgets::{Block, Borders, Paragraph}};
+use uuid::Uuid;
+
+use crate::app::{utils::{sub_rect, handle_menu_input, menu_spans, truncate, hint_spans}, input::{UserInput, Target}, keymap::{Keymap, Action}, ui::{UI, UIContext}, theme::{border_style, regular_style}, widgets::text_input::{TextInput, TextInputEvent}};
+
+const WIDTH: u16 = 60;
+const HEIGHT: u16 = 16;
+
+enum Edit { New, Rename(Uuid), Project(Uuid), Tags(Uuid) }
+
+/// Screen to add, edit and complete tasks
+pub struct TasksUI {
+    tasks: TaskList,
+    selected: usize,
+    editing: Option<(Edit, TextInput)>,
+    keymap: Rc<Keymap>,
+}
+
+impl<B: Backend> UI<B> for TasksUI {
+    fn ui(&mut self, frame: &mut tui::Frame<B>, rect: Rect, input: &mut UserInput) {
+        self.handle_input(input);
+
+        let rect = sub_rect(rect, (WIDTH, HEIGHT));
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(" [ Tasks ] ").title_alignment(Alignment::Center).style(regular_style());
+        let paragraph = Paragraph::new(self.get_spans()).block(block).style(regular_style());
+        frame.render_widget(paragraph, rect);
+
+        if let Some((_, text_input)) = &self.editing {
+            text_input.render(frame, sub_rect(rect, (WIDTH - 10, 3)));
+        }
+    }
+
+    fn get_context(&self) -> Option<UIContext> {
+        Some(UIContext::Tasks)
+    }
+
+    fn handle_context(&mut self, ctx: UIContext) {
+        if let UIContext::Keymap(keymap) = ctx {
+            self.keymap = keymap;
+        }
+    }
+
+    fn typing(&self) -> bool {
+        self.editing.is_some()
+    }
+}
+
+impl TasksUI {
+    pub fn new() -> Self {
+        let tasks = unwrap_err!(TaskList::load(), else => TaskList::default());
+        Self { tasks, selected: 0, editing: None, keymap: Rc::new(Keymap::default_preset()) }
+    }
+
+    fn handle_input(&mut self, input: &mut UserInput) {
+        if let Some((edit, text_input)) = &mut self.editing {
+            match text_input.handle_input(input) {
+                Some(TextInputEvent::Submitted(text)) => {
+                    match *edit {
+                        Edit::New if !text.is_empty() => self.update(|list| { list.add(text, 1); }),
+                        Edit::Rename(id) if !text.is_empty() => self.update(|list| if let Some(task) = list.get_mut(id) { task.title = text }),
+                        Edit::Project(id) => self.update(|list| if let Some(task) = list.get_mut(id) {
+                            task.project = if text.is_empty() { None } else { Some(text) }
+                        }),
+                        Edit::Tags(id) => self.update(|list| if let Some(task) = list.get_mut(id) { task.tags = parse_tags(&text) }),
+                        _ => {},
+                    }
+                    self.editing = None;
+                },
+                Some(_) => self.editing = None,
+                None => {},
+            }
+            return;
+        }
+
+        handle_menu_input(input, &mut self.selected, self.tasks.tasks.len());
+        // The key that opened the list closes it too
+        if input.consume_matches(|x| matches!(x, UserInput::Esc | UserInput::Action(Action::Tasks)), |input| *input = UserInput::Goto(Target::PopStack)).is_some() {
+            return;
+        }
+
+        let key = match input {
+            UserInput::Enter => ' ',
+            UserInput::Char(c) => *c,
+            _ => return,
+        };
+        *input = UserInput::Consumed;
+
+        if key == 'a' {
+            self.editing = Some((Edit::New, TextInput::new("New task")));
+            return;
+        }
+
+        let Some(id) = self.tasks.tasks.get(self.selected).map(|x| x.id) else { return };
+        match key {
+            ' ' => self.update(|list| if let Some(task) = list.get_mut(id) { task.done = !task.done }),
+            '+' => self.update(|list| if let Some(task) = list.get_mut(id) { task.estimate = task.estimate.saturating_add(1) }),
+            '-' => self.update(|list| if let Some(task) = list.get_mut(id) { task.estimate = task.estimate.saturating_sub(1) }),
+            'd' => self.update(|list| list.remove(id)),
+            'e' => {
+                let title = self.tasks.tasks[self.selected].title.clone();
+                self.editing = Some((Edit::Rename(id), TextInput::new("Edit task").with_value(title)));
+            },
+            'p' => {
+                let project = self.tasks.tasks[self.selected].project.clone().unwrap_or_default();
+                self.editing = Some((Edit::Project(id), TextInput::new("Project").with_value(project)));
+            },
+            't' => {
+                let tags = display_tags(&self.tasks.tasks[self.selected].tags);
+                self.editing = Some((Edit::Tags(id), TextInput::new("Tags").with_value(tags)));
+            },
+            _ => {},
+        }
+    }
+
+    /// Applies a change to the persisted task list and reloads it
+    fn update(&mut self, func: impl FnOnce(&mut TaskList)) {
+        unwrap_err!(TaskList::update(func));
+        self.tasks = unwrap_err!(TaskList::load(), else => TaskList::default());
+        self.selected = self.selected.min(self.tasks.tasks.len().saturating_sub(1));
+    }
+
+    fn get_spans(&self) -> Vec<Spans<'_>> {
+        // Scrolls so the selected task is always visible
+        let visible = (HEIGHT - 4) as usize;
+        let offset = (self.selected + 1).saturating_sub(visible);
+
+        let entries = self.tasks.tasks.iter().skip(offset).take(visible).map(|task| {
+            let check = if task.done { "x" } else { " " };
+            let project = task.project.as_ref().map(|x| format!(" +{}", x)).unwrap_or_default();
+            let tags: String = task.tags.iter().map(|x| format!(" @{}", x)).collect();
+            let priority = task.priority.map(|x| format!("({}) ", x)).unwrap_or_default();
+            let text = format!("{}{}{}{}", priority, task.title, project, tags);
+            format!("[{}] {} ({}/{})", check, truncate(&text, (WIDTH - 18) as usize), task.completed, task.estimate)
+        });
+
+        let mut res = if self.tasks.tasks.is_empty() {
+            vec![Spans::from("No tasks yet.")]
+        } else {
+            menu_spans(entries, self.selected - offset)
+        };
+
+        res.resize(visible + 1, Spans::from(""));
+        res.push(hint_spans([
+            Some("a:add e:edit p:project t:tags +/-:estimate".to_string()),
+            self.keymap.hint(&[Action::Confirm], "done", false),
+            Some("d:del".to_string()),
+        ]));
+        res
+    }
+}
@@ -0,0 +1,264 @@
+use std::{fs, sync::RwLock, path::PathBuf};
+
+use anyhow::Result;
+use overfocus::{config::Config, pomodoro::PomodoroStage, storage};
+use thiserror::Error;
+use tui::style::{Style, Color, Modifier};
+
+/// Themes that are always there, the first being the default
+pub const BUILT_IN: [&str; 3] = ["dark", "light", "high-contrast"];
+
+static THEME: RwLock<Theme> = RwLock::new(Theme::dark());
+
+#[derive(Error, Debug)]
+pub enum ThemeError {
+    #[error("No theme is called `{0}`")]
+    UnknownTheme(String),
+    #[error("Line {0} of the theme: expected `role = style`")]
+    InvalidLine(usize),
+    #[error("Unknown theme role `{0}`")]
+    UnknownRole(String),
+    #[error("Unknown color or modifier `{0}`")]
+    InvalidStyle(String),
+}
+
+/// Style of everything that's drawn, by what it's for
+#[derive(Clone, Copy)]
+pub struct Theme {
+    pub regular: Style,
+    pub highlight: Style,
+    pub info_log: Style,
+    pub warn_log: Style,
+    pub err_log: Style,
+    pub work: Style,
+    pub short_break: Style,
+    pub long_break: Style,
+    pub paused: Style,
+    pub border: Style,
+}
+
+pub fn regular_style() -> Style    { theme().regular }
+pub fn highlight_style() -> Style  { theme().highlight }
+pub fn info_log_style() -> Style   { theme().info_log }
+pub fn warn_log_style() -> Style   { theme().warn_log }
+pub fn err_log_style() -> Style    { theme().err_log }
+pub fn paused_style() -> Style     { theme().paused }
+pub fn border_style() -> Style     { theme().border }
+
+pub fn stage_style(stage: PomodoroStage) -> Style {
+    match stage {
+        PomodoroStage::Work => theme().work,
+        PomodoroStage::ShortBreak => theme().short_break,
+        PomodoroStage::LongBreak => theme().long_break,
+    }
+}
+
+fn theme() -> Theme {
+    *THEME.read().unwrap()
+}
+
+/// Picks the theme from the config, going without colors if asked to or if `NO_COLOR` is set
+pub fn init(config: &Config) -> Result<()> {
+    let no_color = std::env::var_os("NO_COLOR").map_or(false, |x| !x.is_empty());
+    let theme = if config.monochrome || no_color { Theme::monochrome() } else { Theme::named(&config.theme)? };
+    *THEME.write().unwrap() = theme;
+    Ok(())
+}
+
+/// Every theme there is, built in ones first
+pub fn available() -> Result<Vec<String>> {
+    let mut res: Vec<String> = BUILT_IN.iter().map(|x| x.to_string()).collect();
+    let dir = themes_dir()?;
+    if dir.exists() {
+        let mut custom = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |x| x == "theme") {
+                custom.extend(path.file_stem().map(|x| x.to_string_lossy().to_string()));
+            }
+        }
+        custom.sort();
+        res.extend(custom);
+    }
+    Ok(res)
+}
+
+/// Custom themes are `<name>.theme` files in here
+pub fn themes_dir() -> Result<PathBuf> {
+    Ok(storage::data_dir()?.join("themes"))
+}
+
+impl Theme {
+    pub const fn dark() -> Self {
+        Self {
+            regular: fg(Color::White),
+            highlight: fg(Color::Yellow),
+            info_log: fg(Color::White),
+            warn_log: fg(Color::Yellow),
+            err_log: fg(Color::Red),
+            work: fg(Color::LightRed),
+            short_break: fg(Color::LightGreen),
+            long_break: fg(Color::LightBlue),
+            paused: fg(Color::Yellow),
+            border: fg(Color::White),
+        }
+    }
+
+    pub const fn light() -> Self {
+        Self {
+            regular: fg(Color::Black),
+            highlight: fg(Color::Blue),
+            info_log: fg(Color::Black),
+            warn_log: fg(Color::Magenta),
+            err_log: fg(Color::Red),
+            work: fg(Color::Red),
+            short_break: fg(Color::Green),
+            long_break: fg(Color::Blue),
+            paused: fg(Color::Magenta),
+            border: fg(Color::DarkGray),
+        }
+    }
+
+    pub const fn high_contrast() -> Self {
+        let bold = Modifier::BOLD;
+        Self {
+            regular: fg(Color::White),
+            highlight: Style { fg: Some(Color::Black), bg: Some(Color::Yellow), add_modifier: bold, sub_modifier: Modifier::empty() },
+            info_log: fg(Color::White),
+            warn_log: Style { fg: Some(Color::Black), bg: Some(Color::Yellow), add_modifier: bold, sub_modifier: Modifier::empty() },
+            err_log: Style { fg: Some(Color::White), bg: Some(Color::Red), add_modifier: bold, sub_modifier: Modifier::empty() },
+            work: modified(fg(Color::LightRed), bold),
+            short_break: modified(fg(Color::LightGreen), bold),
+            long_break: modified(fg(Color::LightCyan), bold),
+            paused: Style { fg: Some(Color::Black), bg: Some(Color::Yellow), add_modifier: bold, sub_modifier: Modifier::empty() },
+            border: modified(fg(Color::White), bold),
+        }
+    }
+
+    /// Tells roles apart with modifiers only, for terminals without colors
+    pub const fn monochrome() -> Self {
+        let plain = Style { fg: None, bg: None, add_modifier: Modifier::empty(), sub_modifier: Modifier::empty() };
+        Self {
+            regular: plain,
+            highlight: modified(plain, Modifier::BOLD),
+            info_log: plain,
+            warn_log: modified(plain, Modifier::BOLD),
+            err_log: modified(plain, Modifier::BOLD.union(Modifier::UNDERLINED)),
+            work: modified(plain, Modifier::BOLD),
+            short_break: plain,
+            long_break: modified(plain, Modifier::ITALIC),
+            paused: modified(plain, Modifier::DIM),
+            border: plain,
+        }
+    }
+
+    /// A built in theme, or one from the themes folder
+    pub fn named(name: &str) -> Result<Self> {
+        match name {
+            "dark" => Ok(Self::dark()),
+            "light" => Ok(Self::light()),
+            "high-contrast" => Ok(Self::high_contrast()),
+            _ => {
+                let path = themes_dir()?.join(format!("{}.theme", name));
+                if !path.exists() {
+                    Err(ThemeError::UnknownTheme(name.to_string()))?;
+                }
+                Self::parse(&fs::read_to_string(path)?)
+            },
+        }
+    }
+
+    /// Reads a theme file made of `role = style` lines and `#` comments, where a style is like `black on yellow bold`.<br>
+    /// Roles left out come from the `base` theme, dark if there's none.
+    pub fn parse(text: &str) -> Result<Self> {
+        let lines: Vec<(usize, &str)> = text.lines().enumerate()
+            .map(|(i, x)| (i + 1, x.trim()))
+            // Comments take whole lines, `#` also starts hex colors
+            .filter(|(_, x)| !x.is_empty() && !x.starts_with('#'))
+            .collect();
+
+        let mut res = Self::dark();
+        let mut roles = Vec::new();
+        for (i, line) in lines {
+            let (role, value) = line.split_once('=').ok_or(ThemeError::InvalidLine(i))?;
+            let (role, value) = (role.trim(), value.trim());
+            if role == "base" {
+                res = Self::named(value)?;
+            } else {
+                roles.push((role, parse_style(value)?));
+            }
+        }
+
+        for (role, style) in roles {
+            *match role {
+                "regular" => &mut res.regular,
+                "highlight" => &mut res.highlight,
+                "info_log" => &mut res.info_log,
+                "warn_log" => &mut res.warn_log,
+                "err_log" => &mut res.err_log,
+                "work" => &mut res.work,
+                "short_break" => &mut res.short_break,
+                "long_break" => &mut res.long_break,
+                "paused" => &mut res.paused,
+                "border" => &mut res.border,
+                other => Err(ThemeError::UnknownRole(other.to_string()))?,
+            } = style;
+        }
+        Ok(res)
+    }
+}
+
+const fn fg(color: Color) -> Style {
+    Style { fg: Some(color), bg: None, add_modifier: Modifier::empty(), sub_modifier: Modifier::empty() }
+}
+
+const fn modified(style: Style, modifier: Modifier) -> Style {
+    Style { add_modifier: style.add_modifier.union(modifier), ..style }
+}
+
+/// Parses colors and modifiers, a color after `on` being the background
+fn parse_style(text: &str) -> Result<Style> {
+    let mut res = Style::default();
+    let mut words = text.split_whitespace().map(|x| x.to_lowercase());
+    while let Some(word) = words.next() {
+        res = match word.as_str() {
+            "on" => res.bg(parse_color(&words.next().unwrap_or_default())?),
+            "bold" => res.add_modifier(Modifier::BOLD),
+            "dim" => res.add_modifier(Modifier::DIM),
+            "italic" => res.add_modifier(Modifier::ITALIC),
+            "underlined" | "underline" => res.add_modifier(Modifier::UNDERLINED),
+            "reversed" | "reverse" => res.add_modifier(Modifier::REVERSED),
+            color => res.fg(parse_color(color)?),
+        };
+    }
+    Ok(res)
+}
+
+/// Parses a color name, a `#rrggbb` or a 256 color palette index
+fn parse_color(text: &str) -> Result<Color> {
+    let color = match text {
+        "default" | "reset" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        hex if hex.starts_with('#') && hex.len() == 7 => {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| ThemeError::InvalidStyle(text.to_string()));
+            Color::Rgb(channel(1)?, channel(3)?, channel(5)?)
+        },
+        index => Color::Indexed(index.parse().map_err(|_| ThemeError::InvalidStyle(text.to_string()))?),
+    };
+    Ok(color)
+}
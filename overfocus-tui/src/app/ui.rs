@@ -1,13 +1,29 @@
-use tui::{backend::Backend, layout::Rect};
+use std::rc::Rc;
 
-use super::input::UserInput;
+use tui::{backend::Backend, layout::Rect, text::Spans};
+
+use super::{input::UserInput, keymap::{Action, Keymap}};
 
 pub trait UI<B: Backend> {
     fn ui(&mut self, frame: &mut tui::Frame<B>, rect: Rect, input: &mut UserInput);
     fn handle_context(&mut self, _ctx: UIContext) { }
     fn get_context(&self) -> Option<UIContext> { None }
+    /// Called on every screen of the stack when the app quits, whichever one is on top
+    fn on_quit(&mut self) { }
+    /// A single line standing in for the screen when the terminal is too small for it
+    fn compact(&self) -> Option<Spans<'static>> { None }
+    /// Actions the screen takes besides moving around and the ones that work everywhere
+    fn actions(&self) -> Vec<Action> { Vec::new() }
+    /// Whether a text box is taking the keys as they are
+    fn typing(&self) -> bool { false }
 }
 
 pub enum UIContext {
-    PomodoroClock { pomodoros: u8 }
+    PomodoroClock { pomodoros: u8 },
+    /// The task list was modified
+    Tasks,
+    /// The inbox was reviewed
+    Inbox,
+    /// The keys in use, given to every screen as it's opened so its hints name them
+    Keymap(Rc<Keymap>),
 }
\ No newline at end of file
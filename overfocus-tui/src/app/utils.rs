@@ -1,19 +1,58 @@
-use tui::{widgets::{Block, Paragraph, Borders}, layout::{Alignment, Rect}, backend::Backend, text::Text};
+use tui::{widgets::{Block, Paragraph, Borders}, layout::{Alignment, Rect}, backend::Backend, text::{Text, Spans, Span}};
 
-use super::styles::regular_style;
+use super::{theme::{border_style, regular_style, highlight_style, info_log_style}, input::UserInput};
 
 pub fn draw_block_with_text<'a, B: Backend, T: Into<Text<'a>>>(text: T, alignment: Alignment, frame: &'a mut tui::Frame<B>, rect: Rect) {
-    let block = Block::default().borders(Borders::ALL);
+    let block = Block::default().borders(Borders::ALL).border_style(border_style());
     let paragraph = Paragraph::new(text).alignment(alignment).block(block).style(regular_style());
     frame.render_widget(paragraph, rect);
 }
 
-/// Returs a Rect centered in the source rect with a given size
+/// Returs a Rect centered in the source rect with a given size, shrunk to fit in it
 pub fn sub_rect(rect: Rect, size: (u16, u16)) -> Rect {
+    let (width, height) = (size.0.min(rect.width), size.1.min(rect.height));
     Rect::new(
-        rect.x + (rect.width / 2) - (size.0 / 2),
-        rect.y + (rect.height / 2) - (size.1 / 2),
-        size.0,
-        size.1
+        rect.x + (rect.width - width) / 2,
+        rect.y + (rect.height - height) / 2,
+        width,
+        height
     )
-}
\ No newline at end of file
+}
+
+/// The part of a Rect inside the bounds, empty if there's none, so nothing gets drawn past them
+pub fn clip(rect: Rect, bounds: Rect) -> Rect {
+    if rect.intersects(bounds) { rect.intersection(bounds) } else { Rect::default() }
+}
+
+/// Moves the cursor of a vertical menu with the up and down arrows
+pub fn handle_menu_input(input: &mut UserInput, selected: &mut usize, len: usize) {
+    input.consume_matches(|x| matches!(x, UserInput::Up), |_| if *selected > 0 { *selected -= 1 });
+    input.consume_matches(|x| matches!(x, UserInput::Down), |_| if *selected + 1 < len { *selected += 1 });
+}
+
+/// Returns the lines of a vertical menu, highlighting the selected entry
+pub fn menu_spans<'a>(entries: impl IntoIterator<Item = String>, selected: usize) -> Vec<Spans<'a>> {
+    entries.into_iter().enumerate().map(|(i, entry)| {
+        if i == selected {
+            Spans::from(Span::styled(format!(">{}", entry), highlight_style()))
+        } else {
+            Spans::from(Span::styled(format!("-{}", entry), regular_style()))
+        }
+    }).collect()
+}
+
+/// Line of key hints, leaving out the ones whose actions have no keys
+pub fn hint_spans(hints: impl IntoIterator<Item = Option<String>>) -> Spans<'static> {
+    let text = hints.into_iter().flatten().collect::<Vec<_>>().join(" ");
+    Spans::from(Span::styled(text, info_log_style()))
+}
+
+/// Cuts a text to a maximum amount of characters, marking it with `~` if it was cut
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut res: String = text.chars().take(max.saturating_sub(1)).collect();
+    res.push('~');
+    res
+}
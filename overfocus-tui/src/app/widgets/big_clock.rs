@@ -0,0 +1,60 @@
+use tui::{backend::Backend, layout::Rect, style::Style, text::Spans, widgets::Paragraph};
+
+const GLYPH_HEIGHT: u16 = 5;
+
+/// Glyphs of the digits and `:`, with `#` for the filled cells
+fn glyph(c: char) -> [&'static str; 5] {
+    match c {
+        '0' => ["###", "# #", "# #", "# #", "###"],
+        '1' => ["  #", "  #", "  #", "  #", "  #"],
+        '2' => ["###", "  #", "###", "#  ", "###"],
+        '3' => ["###", "  #", "###", "  #", "###"],
+        '4' => ["# #", "# #", "###", "  #", "  #"],
+        '5' => ["###", "#  ", "###", "  #", "###"],
+        '6' => ["###", "#  ", "###", "# #", "###"],
+        '7' => ["###", "  #", "  #", "  #", "  #"],
+        '8' => ["###", "# #", "###", "# #", "###"],
+        '9' => ["###", "# #", "###", "  #", "###"],
+        ':' => [" ", "#", " ", "#", " "],
+        _ => ["   ", "   ", "   ", "   ", "   "],
+    }
+}
+
+/// A time drawn with block digits.<br>
+/// At a scale every cell of a glyph takes `2 * scale` columns and `scale` rows, so they look square.
+pub struct BigClock {
+    text: String,
+    style: Style,
+}
+
+impl BigClock {
+    pub fn new(text: impl Into<String>, style: Style) -> Self {
+        Self { text: text.into(), style }
+    }
+
+    /// Width and height it takes at a scale
+    pub fn size(&self, scale: u16) -> (u16, u16) {
+        let cells: usize = self.text.chars().map(|x| glyph(x)[0].len()).sum::<usize>() + self.text.chars().count().saturating_sub(1);
+        (cells as u16 * 2 * scale, GLYPH_HEIGHT * scale)
+    }
+
+    /// The biggest scale that fits in a size, if even the smallest does
+    pub fn fit(&self, width: u16, height: u16) -> Option<u16> {
+        let (w, h) = self.size(1);
+        let scale = (width / w.max(1)).min(height / h);
+        (scale > 0).then_some(scale)
+    }
+
+    /// Draws the clock at the top left of the rect
+    pub fn render<B: Backend>(&self, frame: &mut tui::Frame<B>, rect: Rect, scale: u16) {
+        let mut lines = Vec::new();
+        for row in 0..GLYPH_HEIGHT as usize {
+            let line = self.text.chars().map(|x| glyph(x)[row]).collect::<Vec<_>>().join(" ");
+            let line: String = line.chars().map(|x| if x == '#' { '█' } else { ' ' }).flat_map(|x| std::iter::repeat(x).take(2 * scale as usize)).collect();
+            for _ in 0..scale {
+                lines.push(Spans::from(line.clone()));
+            }
+        }
+        frame.render_widget(Paragraph::new(lines).style(self.style), rect);
+    }
+}
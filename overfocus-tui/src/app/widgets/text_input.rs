@@ -0,0 +1,50 @@
+use tui::{backend::Backend, layout::{Rect, Alignment}, text::{Spans, Span}, widgets::{Block, Borders, Paragraph, Clear}};
+
+use crate::app::{input::UserInput, theme::{border_style, regular_style, highlight_style}};
+
+/// A single line text box that captures every key until it's submitted or cancelled
+pub struct TextInput {
+    title: String,
+    value: String,
+}
+
+pub enum TextInputEvent {
+    Submitted(String),
+    Cancelled,
+}
+
+impl TextInput {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self { title: title.into(), value: String::new() }
+    }
+
+    /// Starts the text box with some text already written
+    pub fn with_value(mut self, value: impl Into<String>) -> Self {
+        self.value = value.into();
+        self
+    }
+
+    /// Consumes any input meant for the text box
+    pub fn handle_input(&mut self, input: &mut UserInput) -> Option<TextInputEvent> {
+        if let UserInput::Char(c) = *input {
+            self.value.push(c);
+        }
+        input.consume_matches(|x| matches!(x, UserInput::Char(_)), |_| ());
+        input.consume_matches(|x| matches!(x, UserInput::Backspace), |_| { self.value.pop(); });
+
+        if input.consume_matches(|x| matches!(x, UserInput::Esc), |_| ()).is_some() {
+            return Some(TextInputEvent::Cancelled);
+        }
+        input.consume_matches(|x| matches!(x, UserInput::Enter), |_| TextInputEvent::Submitted(self.value.trim().to_string()))
+    }
+
+    /// Draws the text box on top of whatever was already in the rect
+    pub fn render<B: Backend>(&self, frame: &mut tui::Frame<B>, rect: Rect) {
+        let block = Block::default().borders(Borders::ALL).border_style(border_style()).title(format!(" {} ", self.title)).title_alignment(Alignment::Center).style(regular_style());
+        let text = Spans::from(vec![Span::raw(self.value.as_str()), Span::styled("_", highlight_style())]);
+        let paragraph = Paragraph::new(text).block(block).style(regular_style());
+
+        frame.render_widget(Clear, rect);
+        frame.render_widget(paragraph, rect);
+    }
+}
@@ -0,0 +1,635 @@
+use std::{path::PathBuf, fs, time::Duration};
+
+use anyhow::Result;
+use chrono::{NaiveDate, DateTime, Utc, Local, Weekday};
+use overfocus::{history::{History, Session, Outcome}, edits::{self, SessionEdit}, pomodoro::PomodoroStage, tasks::{TaskList, Task}, stats::{self, DateRange, Totals}, tags::parse_tags, export::{self, ExportFormat, ExportFilter}, import::{self, ImportFormat}, report::{Report, ReportPeriod, ReportFormat}, config::Config, days, sync, retention};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::app::{theme::{self, Theme}, keymap::{self, Keymap, Action}};
+
+const USAGE: &str = "\
+Usage:
+  overfocus-tui [--profile NAME] [--project NAME] [--tag TAG]...
+  overfocus-tui summary [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--by project|tag]
+  overfocus-tui export --format csv|json|ics|timew|org [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--tag TAG]... [--output FILE]
+  overfocus-tui import FILE [--format todotxt|taskwarrior|markdown] [--watch]
+  overfocus-tui report [--week|--day] [--date YYYY-MM-DD] [--format md|html|text] [--output FILE]
+  overfocus-tui goal [--profile NAME] [--all N] [--weekdays N] [--weekend N] [--mon N] ... [--sun N] [--rest DAYS|none]
+  overfocus-tui vacation [--from YYYY-MM-DD [--to YYYY-MM-DD]] [--clear]
+  overfocus-tui day-start [HOUR]
+  overfocus-tui history list [--from YYYY-MM-DD] [--to YYYY-MM-DD]
+  overfocus-tui history edit ID [--start TIME] [--end TIME] [--task TITLE|none] [--project NAME] [--tags TAGS]
+  overfocus-tui history split ID --at TIME
+  overfocus-tui history merge ID ID
+  overfocus-tui history delete ID
+  overfocus-tui history undo
+  overfocus-tui sync [FOLDER] [--machine NAME]
+  overfocus-tui prune [--keep-months N]
+  overfocus-tui theme [NAME] [--monochrome on|off]
+  overfocus-tui keys [default|vim]
+
+Sessions are picked by the start of their id, as listed. TIME is `YYYY-MM-DD HH:MM`, or `HH:MM` on the day of the session.
+
+A goal of 0 makes a rest day, which like vacations doesn't break streaks. \
+Rest days can also be set for every profile, goals or not, as in `--rest sat,sun`.
+
+Prune rolls sessions older than the retention up into daily totals, which stats and streaks still count. \
+Once a retention is set it's applied on every start.
+
+Themes are dark, light, high-contrast, or any `NAME.theme` file in the themes folder of the data directory. \
+Those are made of `role = style` lines, like `highlight = black on yellow bold`, with a `base = THEME` line for the roles left out. \
+Monochrome, or setting `NO_COLOR`, draws without colors.
+
+Keys starts from the default or vim keymap. Actions get other keys with `keys` in the config, like `\"keys\": { \"pause\": [\"p\", \"ctrl+p\"] }`, \
+which take the place of the ones of the keymap. In the app `?` shows the keys of the screen.
+
+Sync remembers the folder it was last given. If it's a git repository the changes are committed, and when it tracks a remote it's pulled first and pushed after.";
+
+/// Flags setting the goal of single weekdays, Monday first
+const WEEKDAY_FLAGS: [&str; 7] = ["--mon", "--tue", "--wed", "--thu", "--fri", "--sat", "--sun"];
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("Unknown argument `{0}`\n{USAGE}")]
+    UnknownArgument(String),
+    #[error("Missing value for `{0}`\n{USAGE}")]
+    MissingValue(String),
+    #[error("Invalid date `{0}`, expected YYYY-MM-DD")]
+    InvalidDate(String),
+    #[error("The range starts on {0}, after it ends on {1}")]
+    InvertedRange(NaiveDate, NaiveDate),
+    #[error("Invalid grouping `{0}`, expected `project` or `tag`")]
+    InvalidGrouping(String),
+    #[error("Missing `--format`\n{USAGE}")]
+    MissingFormat,
+    #[error("Missing the file to import\n{USAGE}")]
+    MissingFile,
+    #[error("Invalid goal `{0}`, expected a number of pomodoros")]
+    InvalidGoal(String),
+    #[error("Invalid weekday `{0}`, expected `mon` to `sun`")]
+    InvalidWeekday(String),
+    #[error("Invalid hour `{0}`, expected 0 to 23")]
+    InvalidHour(String),
+    #[error("Invalid time `{0}`, expected `YYYY-MM-DD HH:MM` or `HH:MM`")]
+    InvalidTime(String),
+    #[error("Expected `list`, `edit`, `split`, `merge`, `delete` or `undo`\n{USAGE}")]
+    InvalidHistoryAction,
+    #[error("Missing the session to change\n{USAGE}")]
+    MissingSession,
+    #[error("Missing `--at`\n{USAGE}")]
+    MissingSplitTime,
+    #[error("No task or more than one matches `{0}`")]
+    UnknownTask(String),
+    #[error("Invalid number of months `{0}`, expected at least 1")]
+    InvalidMonths(String),
+    #[error("No retention is set, pass one with `--keep-months N`")]
+    NoRetention,
+    #[error("Expected `on` or `off`, got `{0}`")]
+    InvalidSwitch(String),
+}
+
+pub enum Command {
+    /// Opens the terminal interface
+    Run(RunArgs),
+    /// Prints focus time grouped by project or tag
+    Summary { range: DateRange, by: Grouping },
+    /// Writes the history to stdout or a file
+    Export { format: ExportFormat, filter: ExportFilter, output: Option<PathBuf> },
+    /// Imports tasks from a file, keeping it in sync if `watch` is set
+    Import { file: PathBuf, format: Option<ImportFormat>, watch: bool },
+    /// Writes the report of a day or week to stdout or a file
+    Report { period: ReportPeriod, day: NaiveDate, format: ReportFormat, output: Option<PathBuf> },
+    /// Sets the daily goals of a profile, leaving unset weekdays alone, and prints them
+    Goal { profile: Option<String>, weekdays: [Option<u8>; 7], rest: Option<Vec<Weekday>> },
+    /// Adds a vacation or clears them all, then prints them
+    Vacation { add: Option<DateRange>, clear: bool },
+    /// Sets the hour days start at, if given, and prints it
+    DayStart { hour: Option<u8> },
+    /// Lists, corrects or reverts corrections of past sessions
+    History(HistoryAction),
+    /// Syncs the data with a folder, which is remembered if given
+    Sync { dir: Option<PathBuf>, machine: Option<String> },
+    /// Rolls up old sessions, setting the retention first if given
+    Prune { months: Option<u32> },
+    /// Picks the theme or turns colors off, if given, and prints the themes
+    Theme { name: Option<String>, monochrome: Option<bool> },
+    /// Picks the keymap, if given, and prints the keys
+    Keys { preset: Option<String> },
+}
+
+pub enum HistoryAction {
+    List { range: DateRange },
+    /// Times and the task are resolved against the session, so they're kept as text
+    Edit { id: String, start: Option<String>, end: Option<String>, task: Option<String>, project: Option<String>, tags: Option<Vec<String>> },
+    Split { id: String, at: String },
+    Merge { a: String, b: String },
+    Delete { id: String },
+    Undo,
+}
+
+/// Choices the starter screen begins with
+#[derive(Clone, Default)]
+pub struct RunArgs {
+    pub profile: Option<String>,
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Clone, Copy)]
+pub enum Grouping { Project, Tag }
+
+/// Parses the arguments, without the program name
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command> {
+    let mut args = args.into_iter().peekable();
+    let command = match args.peek().map(|x| x.as_str()) {
+        Some("summary") => "summary",
+        Some("export") => "export",
+        Some("import") => "import",
+        Some("report") => "report",
+        Some("goal") => "goal",
+        Some("vacation") => "vacation",
+        Some("day-start") => "day-start",
+        Some("history") => "history",
+        Some("sync") => "sync",
+        Some("prune") => "prune",
+        Some("theme") => "theme",
+        Some("keys") => "keys",
+        _ => "run",
+    };
+    if command != "run" {
+        args.next();
+    }
+
+    let mut project = None;
+    let mut tags = Vec::new();
+    let (mut from, mut to) = (None, None);
+    let mut by = Grouping::Project;
+    let mut format = None;
+    let mut import_format = None;
+    let mut output = None;
+    let mut file = None;
+    let mut watch = false;
+    let mut period = ReportPeriod::Week;
+    let mut report_format = ReportFormat::Text;
+    let mut profile = None;
+    let mut weekdays = [None; 7];
+    let mut rest = None;
+    let mut clear = false;
+    let mut hour = None;
+    let mut machine = None;
+    let mut months = None;
+    let mut theme = None;
+    let mut monochrome = None;
+    let mut preset = None;
+    let mut positional = Vec::new();
+    let (mut start, mut end, mut at, mut task, mut edit_tags) = (None, None, None, None, None);
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| CliError::MissingValue(arg.clone()));
+        match (command, arg.as_str()) {
+            ("run" | "goal", "--profile") => profile = Some(value()?),
+            ("run" | "history", "--project") => project = Some(value()?),
+            ("run" | "export", "--tag") => tags.extend(parse_tags(&value()?)),
+            ("summary" | "export" | "vacation" | "history", "--from") => from = Some(parse_date(&value()?)?),
+            ("summary" | "export" | "vacation" | "history", "--to") => to = Some(parse_date(&value()?)?),
+            ("summary", "--by") => by = match value()?.as_str() {
+                "project" => Grouping::Project,
+                "tag" => Grouping::Tag,
+                other => Err(CliError::InvalidGrouping(other.to_string()))?,
+            },
+            ("export", "--format") => format = Some(value()?.parse::<ExportFormat>()?),
+            ("report", "--week") => period = ReportPeriod::Week,
+            ("report", "--day") => period = ReportPeriod::Day,
+            ("report", "--date") => to = Some(parse_date(&value()?)?),
+            ("report", "--format") => report_format = value()?.parse::<ReportFormat>()?,
+            ("export" | "report", "--output") => output = Some(PathBuf::from(value()?)),
+            ("goal", "--all") => weekdays = [Some(parse_goal(&value()?)?); 7],
+            ("goal", "--weekdays") => weekdays[..5].fill(Some(parse_goal(&value()?)?)),
+            ("goal", "--weekend") => weekdays[5..].fill(Some(parse_goal(&value()?)?)),
+            ("goal", flag) if WEEKDAY_FLAGS.contains(&flag) => {
+                let i = WEEKDAY_FLAGS.iter().position(|x| *x == flag).unwrap();
+                weekdays[i] = Some(parse_goal(&value()?)?);
+            },
+            ("goal", "--rest") => rest = Some(parse_weekdays(&value()?)?),
+            ("vacation", "--clear") => clear = true,
+            ("day-start", value) if hour.is_none() => hour = Some(parse_hour(value)?),
+            ("history", "--start") => start = Some(value()?),
+            ("history", "--end") => end = Some(value()?),
+            ("history", "--at") => at = Some(value()?),
+            ("history", "--task") => task = Some(value()?),
+            ("history", "--tags") => edit_tags = Some(parse_tags(&value()?)),
+            ("history", value) if !value.starts_with("--") => positional.push(value.to_string()),
+            ("import", "--format") => import_format = Some(value()?.parse::<ImportFormat>()?),
+            ("import", "--watch") => watch = true,
+            ("import" | "sync", path) if file.is_none() && !path.starts_with("--") => file = Some(PathBuf::from(path)),
+            ("sync", "--machine") => machine = Some(value()?),
+            ("prune", "--keep-months") => months = Some(parse_months(&value()?)?),
+            ("theme", "--monochrome") => monochrome = Some(parse_switch(&value()?)?),
+            ("theme", name) if theme.is_none() && !name.starts_with("--") => theme = Some(name.to_string()),
+            ("keys", name) if preset.is_none() && !name.starts_with("--") => preset = Some(name.to_string()),
+            _ => Err(CliError::UnknownArgument(arg.clone()))?,
+        }
+    }
+
+    Ok(match command {
+        "summary" => Command::Summary { range: fill_range(from, to, DateRange::last_days(7))?, by },
+        "export" => {
+            // Without dates everything gets exported
+            let range = (from.is_some() || to.is_some()).then(|| fill_range(from, to, DateRange { from: NaiveDate::MIN, to: NaiveDate::MAX })).transpose()?;
+            Command::Export { format: format.ok_or(CliError::MissingFormat)?, filter: ExportFilter { range, tags }, output }
+        },
+        "import" => Command::Import { file: file.ok_or(CliError::MissingFile)?, format: import_format, watch },
+        "report" => Command::Report { period, day: to.unwrap_or_else(days::today), format: report_format, output },
+        "goal" => Command::Goal { profile, weekdays, rest },
+        "day-start" => Command::DayStart { hour },
+        "history" => {
+            let mut positional = positional.into_iter();
+            let action = positional.next();
+            let mut id = || positional.next().ok_or(CliError::MissingSession);
+            Command::History(match action.as_deref() {
+                Some("list") | None => HistoryAction::List { range: fill_range(from, to, DateRange::last_days(7))? },
+                Some("edit") => HistoryAction::Edit { id: id()?, start, end, task, project, tags: edit_tags },
+                Some("split") => HistoryAction::Split { id: id()?, at: at.ok_or(CliError::MissingSplitTime)? },
+                Some("merge") => HistoryAction::Merge { a: id()?, b: id()? },
+                Some("delete") => HistoryAction::Delete { id: id()? },
+                Some("undo") => HistoryAction::Undo,
+                Some(_) => Err(CliError::InvalidHistoryAction)?,
+            })
+        },
+        "sync" => Command::Sync { dir: file, machine },
+        "prune" => Command::Prune { months },
+        "theme" => Command::Theme { name: theme, monochrome },
+        "keys" => Command::Keys { preset },
+        // A vacation without an end is a single day off
+        "vacation" => Command::Vacation { add: from.map(|from| fill_range(Some(from), to, DateRange { from, to: from })).transpose()?, clear },
+        _ => Command::Run(RunArgs { profile, project, tags }),
+    })
+}
+
+/// Completes a range with the ends of a default one, refusing it if it ends before it starts
+fn fill_range(from: Option<NaiveDate>, to: Option<NaiveDate>, default: DateRange) -> Result<DateRange> {
+    let range = DateRange { from: from.unwrap_or(default.from), to: to.unwrap_or(default.to) };
+    if range.from > range.to {
+        Err(CliError::InvertedRange(range.from, range.to))?
+    }
+    Ok(range)
+}
+
+fn parse_goal(text: &str) -> Result<u8> {
+    Ok(text.parse().map_err(|_| CliError::InvalidGoal(text.to_string()))?)
+}
+
+/// `sat,sun`, or `none` for no day at all
+fn parse_weekdays(text: &str) -> Result<Vec<Weekday>> {
+    if text == "none" {
+        return Ok(Vec::new());
+    }
+    Ok(text.split(',').map(|x| x.trim().parse().map_err(|_| CliError::InvalidWeekday(x.to_string()))).collect::<Result<_, _>>()?)
+}
+
+fn parse_hour(text: &str) -> Result<u8> {
+    Ok(text.parse().ok().filter(|x| *x < 24).ok_or_else(|| CliError::InvalidHour(text.to_string()))?)
+}
+
+fn parse_months(text: &str) -> Result<u32> {
+    Ok(text.parse().ok().filter(|x| *x > 0).ok_or_else(|| CliError::InvalidMonths(text.to_string()))?)
+}
+
+fn parse_switch(text: &str) -> Result<bool> {
+    match text {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(CliError::InvalidSwitch(text.to_string()))?,
+    }
+}
+
+fn parse_time(text: &str, reference: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    Ok(days::parse_time(text, reference).ok_or_else(|| CliError::InvalidTime(text.to_string()))?)
+}
+
+fn parse_date(text: &str) -> Result<NaiveDate> {
+    Ok(NaiveDate::parse_from_str(text, "%Y-%m-%d").map_err(|_| CliError::InvalidDate(text.to_string()))?)
+}
+
+/// Prints the focus summary to stdout
+pub fn summary(range: DateRange, by: Grouping) -> Result<()> {
+    let history = History::load()?;
+    let (groups, name) = match by {
+        Grouping::Project => (stats::by_project(&history, range), "project"),
+        Grouping::Tag => (stats::by_tag(&history, range), "tag"),
+    };
+
+    println!("Focus from {} to {}, by {}", range.from, range.to, name);
+    if groups.is_empty() {
+        println!("  No work recorded.");
+    }
+
+    let mut total = Totals::default();
+    for (group, totals) in &groups {
+        println!("  {:<20} {:>8} {:>4} pomodoros", group, format_secs(totals.focus_secs), totals.pomodoros);
+        total.focus_secs += totals.focus_secs;
+        total.pomodoros += totals.pomodoros;
+    }
+
+    // Tags overlap, so their sum means nothing
+    if let Grouping::Project = by {
+        println!("  {:<20} {:>8} {:>4} pomodoros", "Total", format_secs(total.focus_secs), total.pomodoros);
+    }
+    Ok(())
+}
+
+/// Exports the history to a file, or stdout if none is given
+pub fn export(format: ExportFormat, filter: ExportFilter, output: Option<PathBuf>) -> Result<()> {
+    let text = export::export(&History::load()?, &TaskList::load()?, format, &filter)?;
+    match output {
+        Some(path) => fs::write(path, text)?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Imports tasks from a file, then keeps syncing it until interrupted if `watch` is set
+pub fn import(file: PathBuf, format: Option<ImportFormat>, watch: bool) -> Result<()> {
+    let format = match format {
+        Some(format) => format,
+        None => ImportFormat::detect(&file)?,
+    };
+
+    let summary = import::import(&file, format)?;
+    println!("Imported {}: {}", file.display(), summary);
+
+    if watch {
+        println!("Watching for changes, press Ctrl+C to stop.");
+        import::watch(Duration::from_secs(2), |path, summary| println!("Synced {}: {}", path.display(), summary));
+    }
+    Ok(())
+}
+
+/// Writes the report of the day or week containing `day`, to a file or stdout
+pub fn report(period: ReportPeriod, day: NaiveDate, format: ReportFormat, output: Option<PathBuf>) -> Result<()> {
+    let text = Report::new(&History::load()?, &TaskList::load()?, &Config::load()?, period, day).render(format);
+    match output {
+        Some(path) => fs::write(path, text)?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Updates the goals of a profile, the active one by default, and the rest days, then prints them
+pub fn goal(profile: Option<String>, weekdays: [Option<u8>; 7], rest: Option<Vec<Weekday>>) -> Result<()> {
+    let mut config = Config::load()?;
+    let profile = profile.unwrap_or_else(|| config.profile.clone());
+    let goals = config.goals.entry(profile.clone()).or_default();
+    for (goal, new) in goals.weekdays.iter_mut().zip(weekdays) {
+        if let Some(new) = new {
+            *goal = new;
+        }
+    }
+    let goals = *goals;
+    if let Some(rest) = &rest {
+        config.rest_days = rest.clone();
+    }
+    if weekdays.iter().any(|x| x.is_some()) || rest.is_some() {
+        config.save()?;
+    }
+
+    println!("Daily goals of `{}`", profile);
+    for (flag, goal) in WEEKDAY_FLAGS.iter().zip(goals.weekdays) {
+        let goal = match goal {
+            0 => "rest".to_string(),
+            1 => "1 pomodoro".to_string(),
+            n => format!("{} pomodoros", n),
+        };
+        println!("  {}  {}", &flag[2..], goal);
+    }
+    if !config.rest_days.is_empty() {
+        let days: Vec<String> = config.rest_days.iter().map(|x| x.to_string().to_lowercase()).collect();
+        println!("Rest days: {}", days.join(", "));
+    }
+    Ok(())
+}
+
+/// Adds a vacation or clears them all, then prints the ones left
+pub fn vacation(add: Option<DateRange>, clear: bool) -> Result<()> {
+    let mut config = Config::load()?;
+    if clear {
+        config.vacations.clear();
+    }
+    config.vacations.extend(add);
+    if clear || add.is_some() {
+        config.save()?;
+    }
+
+    if config.vacations.is_empty() {
+        println!("No vacations.");
+    }
+    for range in &config.vacations {
+        println!("  {} to {}", range.from, range.to);
+    }
+    Ok(())
+}
+
+/// Sets the hour days start at, then prints it
+pub fn day_start(hour: Option<u8>) -> Result<()> {
+    let mut config = Config::load()?;
+    if let Some(hour) = hour {
+        config.day_start_hour = hour;
+        config.save()?;
+    }
+
+    println!("Days start at {:02}:00, work before that counts for the day before.", config.day_start_hour);
+    Ok(())
+}
+
+/// Syncs with a folder, remembering it and the machine name if given, then prints what changed
+pub fn sync(dir: Option<PathBuf>, machine: Option<String>) -> Result<()> {
+    if dir.is_some() || machine.is_some() {
+        let mut config = Config::load()?;
+        if let Some(dir) = dir {
+            if !dir.is_dir() {
+                return Err(sync::SyncError::NotAFolder(dir).into());
+            }
+            config.sync_dir = Some(dir.canonicalize()?);
+        }
+        config.machine = machine.or(config.machine);
+        config.save()?;
+    }
+
+    let report = sync::sync_configured()?;
+    println!("Synced with {}: {} changes pulled, {} pushed.", report.dir.display(), report.pulled, report.pushed);
+    for conflict in &report.conflicts {
+        println!("  Conflict: {}, kept the synced version", conflict);
+    }
+    if !report.conflicts.is_empty() {
+        println!("Sessions changed here can be brought back with `history undo`.");
+    }
+    Ok(())
+}
+
+/// Rolls up the sessions older than the retention, saving it first if given, then prints what was done
+pub fn prune(months: Option<u32>) -> Result<()> {
+    let mut config = Config::load()?;
+    if let Some(months) = months {
+        config.retention_months = Some(months);
+        config.save()?;
+    }
+
+    let months = config.retention_months.ok_or(CliError::NoRetention)?;
+    let report = retention::prune(months)?;
+    match report.sessions {
+        0 => println!("Nothing from before {} to prune.", report.before),
+        n => println!("Rolled up {} sessions of {} days from before {}.", n, report.days, report.before),
+    }
+    Ok(())
+}
+
+/// Saves the theme, once it's known to load, and the monochrome switch if given, then prints the themes
+pub fn theme(name: Option<String>, monochrome: Option<bool>) -> Result<()> {
+    let mut config = Config::load()?;
+    if let Some(name) = name {
+        Theme::named(&name)?;
+        config.theme = name;
+    }
+    config.monochrome = monochrome.unwrap_or(config.monochrome);
+    config.save()?;
+
+    for name in theme::available()? {
+        println!("{} {}", if name == config.theme { "*" } else { " " }, name);
+    }
+    if config.monochrome {
+        println!("Monochrome is on, so colors are off.");
+    }
+    println!("Custom themes go in {}.", theme::themes_dir()?.display());
+    Ok(())
+}
+
+/// Saves the keymap, if given, then prints the keys of every action, overrides included
+pub fn keys(preset: Option<String>) -> Result<()> {
+    let mut config = Config::load()?;
+    if let Some(preset) = preset {
+        Keymap::preset(&preset)?;
+        config.keymap = preset;
+        config.save()?;
+    }
+
+    let keymap = Keymap::from_config(&config)?;
+    for name in keymap::PRESETS {
+        println!("{} {}", if name == config.keymap { "*" } else { " " }, name);
+    }
+    println!();
+    for action in Action::ALL {
+        let keys = keymap.keys(action).iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        println!("  {:<8} {:<16} {}", action.name(), keys.join(" "), action.description());
+    }
+    Ok(())
+}
+
+/// Runs a history action, printing what it did
+pub fn history(action: HistoryAction) -> Result<()> {
+    let history = History::load()?;
+    match action {
+        HistoryAction::List { range } => {
+            let tasks = TaskList::load()?;
+            let sessions: Vec<&Session> = history.sessions.iter().filter(|x| range.contains(days::local_day(x.start))).collect();
+            if sessions.is_empty() {
+                println!("No sessions from {} to {}.", range.from, range.to);
+            }
+            for session in sessions {
+                println!("{}", describe_session(session, &tasks));
+            }
+        },
+        HistoryAction::Edit { id, start, end, task, project, tags } => {
+            let id = edits::find(&history, &id)?;
+            let session = history.get(id).unwrap();
+            let task = match task.as_deref() {
+                Some("none") => Some(None),
+                Some(title) => Some(Some(find_task(&TaskList::load()?, title)?)),
+                None => None,
+            };
+            edits::edit(id, SessionEdit {
+                start: start.map(|x| parse_time(&x, session.start)).transpose()?,
+                end: end.map(|x| parse_time(&x, session.start)).transpose()?,
+                task,
+                project: project.map(|x| if x.is_empty() || x == "none" { None } else { Some(x) }),
+                tags,
+            })?;
+            println!("Edited the session.");
+        },
+        HistoryAction::Split { id, at } => {
+            let id = edits::find(&history, &id)?;
+            let new = edits::split(id, parse_time(&at, history.get(id).unwrap().start)?)?;
+            println!("Split the session, the second half is {}.", short_id(new));
+        },
+        HistoryAction::Merge { a, b } => {
+            edits::merge(edits::find(&history, &a)?, edits::find(&history, &b)?)?;
+            println!("Merged the sessions.");
+        },
+        HistoryAction::Delete { id } => {
+            edits::delete(edits::find(&history, &id)?)?;
+            println!("Deleted the session.");
+        },
+        HistoryAction::Undo => println!("Undid: {}.", edits::undo()?),
+    }
+    Ok(())
+}
+
+/// The id of the only task whose title matches, exactly or in part, ignoring case
+fn find_task(tasks: &TaskList, title: &str) -> Result<Uuid> {
+    let title = title.to_lowercase();
+    let exact: Vec<&Task> = tasks.tasks.iter().filter(|x| x.title.to_lowercase() == title).collect();
+    let partial: Vec<&Task> = tasks.tasks.iter().filter(|x| x.title.to_lowercase().contains(&title)).collect();
+    match (exact.as_slice(), partial.as_slice()) {
+        ([task], _) | ([], [task]) => Ok(task.id),
+        _ => Err(CliError::UnknownTask(title))?,
+    }
+}
+
+/// One line per session, as `history list` shows them
+fn describe_session(session: &Session, tasks: &TaskList) -> String {
+    let stage = match session.stage {
+        PomodoroStage::Work => "work",
+        PomodoroStage::ShortBreak => "break",
+        PomodoroStage::LongBreak => "long break",
+    };
+    let outcome = match &session.outcome {
+        Outcome::Completed => "completed".to_string(),
+        Outcome::Abandoned(reason) => reason.to_string(),
+    };
+    let task = session.task.and_then(|id| tasks.get(id)).map(|x| format!(" \"{}\"", x.title)).unwrap_or_default();
+    let project = session.project.as_ref().map(|x| format!(" +{}", x)).unwrap_or_default();
+    let tags: String = session.tags.iter().map(|x| format!(" @{}", x)).collect();
+
+    let start = session.start.with_timezone(&Local);
+    let end = session.end.with_timezone(&Local);
+    format!("  {}  {} {}-{}  {:<10} {:>4}m  {}{}{}{}", short_id(session.id), start.format("%Y-%m-%d"), start.format("%H:%M"), end.format("%H:%M"),
+        stage, session.focus_secs / 60, outcome, task, project, tags)
+}
+
+/// Enough of an id to pick a session with
+fn short_id(id: Uuid) -> String {
+    id.to_string()[..8].to_string()
+}
+
+fn format_secs(secs: u64) -> String {
+    format!("{}h {:02}m", secs / 3600, (secs / 60) % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &str) -> Result<Command> {
+        parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn ranges_ending_before_they_start_are_refused() {
+        for command in ["summary", "export --format csv", "history list", "vacation"] {
+            let err = parse_args(&format!("{} --from 2023-05-02 --to 2023-05-01", command)).err().unwrap();
+            assert!(matches!(err.downcast_ref(), Some(CliError::InvertedRange(..))), "{}", command);
+        }
+        assert!(matches!(parse_args("summary --from 2023-05-01 --to 2023-05-01"), Ok(Command::Summary { .. })));
+        assert!(matches!(parse_args("export --format csv --from 2023-05-02"), Ok(Command::Export { .. })));
+    }
+}
@@ -0,0 +1,60 @@
+use std::{fs, panic::PanicHookInfo, path::PathBuf, backtrace::Backtrace, fmt::Write};
+
+use anyhow::Result;
+use chrono::Local;
+use overfocus::{storage, logger::{Logger, LogKind}, pomodoro::{Pomodoro, PomodoroSnapshot}};
+
+/// How many of the last logs go in a report
+const RECENT_LOGS: usize = 20;
+
+/// Saves the running clock so the next start restores it, then writes what's known about the crash
+/// to the crashes folder of the data directory
+pub fn write_report(info: &PanicHookInfo) -> Result<PathBuf> {
+    let snapshot = Pomodoro::save_snapshot();
+    let now = Local::now();
+
+    let mut text = String::new();
+    writeln!(text, "Overfocus {} crashed on {}", env!("CARGO_PKG_VERSION"), now.format("%Y-%m-%d %H:%M:%S"))?;
+    writeln!(text, "\n{}", info)?;
+
+    writeln!(text, "\nTimer:")?;
+    match snapshot {
+        Ok(Some(snapshot)) => writeln!(text, "{}\n  Saved, it's restored on the next start.", describe(&snapshot))?,
+        Ok(None) => writeln!(text, "  No pomodoro was running.")?,
+        Err(e) => writeln!(text, "  Couldn't be saved: {}", e)?,
+    }
+
+    writeln!(text, "\nLast logs:")?;
+    for log in Logger::recent(RECENT_LOGS) {
+        let kind = match log.1 {
+            LogKind::Info => "info",
+            LogKind::Warn => "warn",
+            LogKind::Err => "err",
+        };
+        writeln!(text, "  [{:02}:{:02}:{:02}] {}: {}", log.2 / 3600, (log.2 / 60) % 60, log.2 % 60, kind, log.0)?;
+    }
+
+    writeln!(text, "\nBacktrace:\n{}", Backtrace::force_capture())?;
+
+    let dir = storage::data_dir()?.join("crashes");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("crash-{}.txt", now.format("%Y%m%d-%H%M%S")));
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+fn describe(snapshot: &PomodoroSnapshot) -> String {
+    let state = if snapshot.voided.is_some() { ", voided" } else if snapshot.paused { ", paused" } else { "" };
+    let mut res = format!("  {:?} stage {} of the set, {}:{:02} in{}", snapshot.stage, snapshot.repetitions + 1, snapshot.seconds / 60, snapshot.seconds % 60, state);
+    res += &format!("\n  Session {}, started {}", snapshot.session, snapshot.stage_start.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"));
+    if let Some(task) = snapshot.task {
+        res += &format!("\n  Task {}", task);
+    }
+    if let Some(project) = &snapshot.project {
+        res += &format!("\n  Project {}", project);
+    }
+    if !snapshot.tags.is_empty() {
+        res += &format!("\n  Tags {}", snapshot.tags.join(", "));
+    }
+    res
+}
@@ -4,34 +4,97 @@
 use std::time::Duration;
 
 use app::App;
-use crossterm::{event::{EnableMouseCapture, DisableMouseCapture}, terminal::{enable_raw_mode, EnterAlternateScreen, disable_raw_mode, LeaveAlternateScreen}, execute};
-use tui::{backend::{CrosstermBackend, Backend}, Terminal};
+use crossterm::{event::{EnableMouseCapture, DisableMouseCapture}, cursor::Show, terminal::{enable_raw_mode, EnterAlternateScreen, disable_raw_mode, LeaveAlternateScreen}, execute};
+use tui::{backend::CrosstermBackend, Terminal};
 
 type BackendTerminal = Terminal<CrosstermBackend<std::io::Stdout>>;
 
 mod app;
+mod cli;
+mod crash;
 
 fn main() {
-    let terminal = setup_terminal().unwrap();
+    match overfocus::storage::migrate() {
+        Ok(Some(report)) => eprintln!("Migrated the data from schema v{} to v{}, the old files are in {}", report.from, report.to, report.backup.display()),
+        Ok(None) => {},
+        Err(e) => return exit_on_err(Err(e)),
+    }
+    if let Err(e) = overfocus::days::init() {
+        return exit_on_err(Err(e));
+    }
+
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(cli::Command::Run(args)) => args,
+        Ok(cli::Command::Summary { range, by }) => return exit_on_err(cli::summary(range, by)),
+        Ok(cli::Command::Export { format, filter, output }) => return exit_on_err(cli::export(format, filter, output)),
+        Ok(cli::Command::Import { file, format, watch }) => return exit_on_err(cli::import(file, format, watch)),
+        Ok(cli::Command::Report { period, day, format, output }) => return exit_on_err(cli::report(period, day, format, output)),
+        Ok(cli::Command::Goal { profile, weekdays, rest }) => return exit_on_err(cli::goal(profile, weekdays, rest)),
+        Ok(cli::Command::Vacation { add, clear }) => return exit_on_err(cli::vacation(add, clear)),
+        Ok(cli::Command::DayStart { hour }) => return exit_on_err(cli::day_start(hour)),
+        Ok(cli::Command::History(action)) => return exit_on_err(cli::history(action)),
+        Ok(cli::Command::Sync { dir, machine }) => return exit_on_err(cli::sync(dir, machine)),
+        Ok(cli::Command::Prune { months }) => return exit_on_err(cli::prune(months)),
+        Ok(cli::Command::Theme { name, monochrome }) => return exit_on_err(cli::theme(name, monochrome)),
+        Ok(cli::Command::Keys { preset }) => return exit_on_err(cli::keys(preset)),
+        Err(e) => return exit_on_err(Err(e)),
+    };
+
+    // Only before starting the app, `prune` does it itself and the other commands shouldn't fail on it
+    if let Err(e) = overfocus::retention::prune_configured() {
+        return exit_on_err(Err(e));
+    }
+
+    install_panic_hook();
+    let terminal = match setup_terminal() {
+        Ok(x) => x,
+        Err(e) => {
+            _ = restore_terminal();
+            return exit_on_err(Err(e));
+        },
+    };
 
     // Run application
-    let mut app = App::new(terminal);
-    app.run(Duration::from_millis(200));
+    let mut app = App::new(terminal, args);
+    let res = app.run(Duration::from_secs(1));
 
-    terminate_terminal(app).unwrap();
+    _ = app.terminal_mut().show_cursor();
+    exit_on_err(restore_terminal().and(res));
+}
+
+/// Prints the error of a command line action and exits with a failure code
+fn exit_on_err(res: anyhow::Result<()>) {
+    if let Err(e) = res {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
 
 fn setup_terminal() -> anyhow::Result<BackendTerminal> {
     enable_raw_mode()?;
     let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture).unwrap();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     Ok(Terminal::new(backend)?)
 }
 
-fn terminate_terminal<B: Backend + std::io::Write>(mut app: App<B>) -> anyhow::Result<()> {
+fn restore_terminal() -> anyhow::Result<()> {
     disable_raw_mode()?;
-    execute!(app.terminal_mut().backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
-    app.terminal_mut().show_cursor()?;
+    execute!(std::io::stdout(), LeaveAlternateScreen, DisableMouseCapture, Show)?;
     Ok(())
+}
+
+/// Puts the terminal back before anything gets printed and writes a crash report.<br>
+/// A panic on any thread leaves the app broken, so it exits right after.
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        _ = restore_terminal();
+        default_hook(info);
+        match crash::write_report(info) {
+            Ok(path) => eprintln!("Overfocus crashed, the report is in {}", path.display()),
+            Err(e) => eprintln!("Overfocus crashed, and the report couldn't be written: {}", e),
+        }
+        std::process::exit(101);
+    }));
 }
\ No newline at end of file
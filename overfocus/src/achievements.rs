@@ -0,0 +1,129 @@
+use std::{sync::Mutex, collections::BTreeMap};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc, NaiveDate};
+use serde::{Serialize, Deserialize};
+
+use crate::{storage, history::History, config::Config, goals, stats};
+
+pub(crate) const ACHIEVEMENTS_FILE: &str = "achievements.json";
+
+/// Serializes every read-modify-write of the achievements file
+static ACHIEVEMENTS_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub enum Milestone {
+    FirstPomodoro,
+    Pomodoros100,
+    Pomodoros1000,
+    Streak10,
+    Streak30,
+    /// Every work stage up to a long break, without a single interruption
+    CleanSet,
+}
+
+/// What milestones are checked against
+#[derive(Clone, Copy, Default)]
+pub struct Progress {
+    pub pomodoros: usize,
+    pub streak: usize,
+    /// Whether a set was just finished without interruptions
+    pub clean_set: bool,
+}
+
+/// Milestones reached so far and when, persisted in the data directory
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Achievements {
+    pub unlocked: BTreeMap<Milestone, DateTime<Utc>>,
+}
+
+impl Milestone {
+    pub const ALL: [Milestone; 6] = [
+        Milestone::FirstPomodoro, Milestone::Pomodoros100, Milestone::Pomodoros1000,
+        Milestone::Streak10, Milestone::Streak30, Milestone::CleanSet,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Milestone::FirstPomodoro => "First tomato",
+            Milestone::Pomodoros100 => "Centurion",
+            Milestone::Pomodoros1000 => "Tomato farmer",
+            Milestone::Streak10 => "On a roll",
+            Milestone::Streak30 => "Habit formed",
+            Milestone::CleanSet => "Deep focus",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Milestone::FirstPomodoro => "Complete a pomodoro",
+            Milestone::Pomodoros100 => "Complete 100 pomodoros",
+            Milestone::Pomodoros1000 => "Complete 1000 pomodoros",
+            Milestone::Streak10 => "Keep a 10 day streak",
+            Milestone::Streak30 => "Keep a 30 day streak",
+            Milestone::CleanSet => "Finish a full set without interruptions",
+        }
+    }
+
+    fn reached(&self, progress: &Progress) -> bool {
+        match self {
+            Milestone::FirstPomodoro => progress.pomodoros >= 1,
+            Milestone::Pomodoros100 => progress.pomodoros >= 100,
+            Milestone::Pomodoros1000 => progress.pomodoros >= 1000,
+            Milestone::Streak10 => progress.streak >= 10,
+            Milestone::Streak30 => progress.streak >= 30,
+            Milestone::CleanSet => progress.clean_set,
+        }
+    }
+}
+
+impl Progress {
+    /// Progress according to the persisted history and goals
+    pub fn load(today: NaiveDate) -> Result<Self> {
+        let history = History::load()?;
+        let config = Config::load()?;
+        Ok(Self {
+            pomodoros: stats::pomodoro_count(&history),
+            streak: goals::streak(&history, &config.goals(), &config, today),
+            clean_set: false,
+        })
+    }
+}
+
+impl Achievements {
+    pub fn load() -> Result<Self> {
+        let _lock = ACHIEVEMENTS_LOCK.lock().unwrap();
+        storage::load(ACHIEVEMENTS_FILE)
+    }
+
+    /// Unlocks every milestone the progress reaches, returning the ones that weren't yet
+    pub fn unlock(progress: &Progress) -> Result<Vec<Milestone>> {
+        let _lock = ACHIEVEMENTS_LOCK.lock().unwrap();
+        let mut achievements: Achievements = storage::load(ACHIEVEMENTS_FILE)?;
+
+        let new: Vec<Milestone> = Milestone::ALL.into_iter()
+            .filter(|x| !achievements.unlocked.contains_key(x) && x.reached(progress))
+            .collect();
+        if new.is_empty() {
+            return Ok(new);
+        }
+
+        for milestone in &new {
+            achievements.unlocked.insert(*milestone, Utc::now());
+        }
+        storage::save(ACHIEVEMENTS_FILE, &achievements)?;
+        Ok(new)
+    }
+
+    /// Adds the milestones unlocked elsewhere, keeping the earliest time each one was reached
+    pub fn merge(other: &Achievements) -> Result<Self> {
+        let _lock = ACHIEVEMENTS_LOCK.lock().unwrap();
+        let mut achievements: Achievements = storage::load(ACHIEVEMENTS_FILE)?;
+        for (milestone, time) in &other.unlocked {
+            let unlocked = achievements.unlocked.entry(*milestone).or_insert(*time);
+            *unlocked = (*unlocked).min(*time);
+        }
+        storage::save(ACHIEVEMENTS_FILE, &achievements)?;
+        Ok(achievements)
+    }
+}
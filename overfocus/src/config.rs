@@ -0,0 +1,99 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::Result;
+use chrono::{NaiveDate, Weekday, Datelike};
+use serde::{Serialize, Deserialize};
+
+use crate::{storage, goals::DailyGoals, stats::DateRange};
+
+const CONFIG_FILE: &str = "config.json";
+
+/// Profile used when none was ever picked
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// User settings, persisted in the data directory
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub strict: StrictMode,
+    /// Asks for a focus rating and a note when a work stage ends
+    pub reflect: bool,
+    /// Keeps the files tasks were imported from in sync while the app runs
+    pub watch_imports: bool,
+    /// The profile whose goals are being followed
+    pub profile: String,
+    /// Daily goals of each profile
+    pub goals: BTreeMap<String, DailyGoals>,
+    /// Days off that don't break streaks
+    pub vacations: Vec<DateRange>,
+    /// Weekdays that don't break streaks, whether goals are set or not
+    pub rest_days: Vec<Weekday>,
+    /// Hour (0-23) days start at, work before it counts for the day before
+    pub day_start_hour: u8,
+    /// Folder or git repository the data is synced with
+    pub sync_dir: Option<PathBuf>,
+    /// Name this machine's copy of the data goes by in the sync folder, the host name if not set
+    pub machine: Option<String>,
+    /// Months sessions are kept for before they're rolled up into daily totals, forever if not set
+    pub retention_months: Option<u32>,
+    /// Theme the app is drawn with, built in or from the themes folder
+    pub theme: String,
+    /// Draws without colors, as if `NO_COLOR` was set
+    pub monochrome: bool,
+    /// Key bindings the app starts from, `default` or `vim`
+    pub keymap: String,
+    /// Keys replacing the ones of the keymap, by action
+    pub keys: BTreeMap<String, Vec<String>>,
+}
+
+/// Strict mode follows the classic rule that an interrupted pomodoro is void.<br>
+/// Pausing during work for longer than `grace_secs` voids the current pomodoro.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrictMode {
+    pub enabled: bool,
+    pub grace_secs: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { strict: StrictMode::default(), reflect: true, watch_imports: false, profile: DEFAULT_PROFILE.to_string(), goals: BTreeMap::new(), vacations: Vec::new(), rest_days: Vec::new(), day_start_hour: 0, sync_dir: None, machine: None, retention_months: None, theme: "dark".to_string(), monochrome: false, keymap: "default".to_string(), keys: BTreeMap::new() }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        storage::load(CONFIG_FILE)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        storage::save(CONFIG_FILE, self)
+    }
+
+    /// Goals of the active profile
+    pub fn goals(&self) -> DailyGoals {
+        self.goals.get(&self.profile).copied().unwrap_or_default()
+    }
+
+    pub fn goals_mut(&mut self) -> &mut DailyGoals {
+        self.goals.entry(self.profile.clone()).or_default()
+    }
+
+    pub fn on_vacation(&self, day: NaiveDate) -> bool {
+        self.vacations.iter().any(|x| x.contains(day))
+    }
+
+    pub fn is_rest_day(&self, day: NaiveDate) -> bool {
+        self.rest_days.contains(&day.weekday())
+    }
+
+    /// Every profile with goals, plus the active one, sorted by name
+    pub fn profiles(&self) -> Vec<String> {
+        let mut res: Vec<String> = self.goals.keys().cloned().collect();
+        if !res.contains(&self.profile) {
+            res.push(self.profile.clone());
+            res.sort();
+        }
+        res
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc, NaiveDate, NaiveDateTime, NaiveTime, Local, TimeZone, Timelike, Duration};
+
+use crate::config::Config;
+
+/// Hour local days start at, so late work counts for the day before
+static DAY_START: AtomicU8 = AtomicU8::new(0);
+
+/// Takes the day start hour from the config
+pub fn init() -> Result<()> {
+    set_day_start(Config::load()?.day_start_hour);
+    Ok(())
+}
+
+pub fn set_day_start(hour: u8) {
+    DAY_START.store(hour.min(23), Ordering::Relaxed);
+}
+
+pub fn day_start() -> u8 {
+    DAY_START.load(Ordering::Relaxed)
+}
+
+/// The local day a timestamp counts for.<br>
+/// Goes by the wall clock, so the boundary stays at the same hour across DST changes.
+pub fn local_day(time: DateTime<Utc>) -> NaiveDate {
+    day_of(time.with_timezone(&Local).naive_local(), day_start())
+}
+
+/// The day a wall clock time counts for when days start at `day_start`
+fn day_of(time: NaiveDateTime, day_start: u8) -> NaiveDate {
+    (time - Duration::hours(day_start as i64)).date()
+}
+
+/// The local day it is right now
+pub fn today() -> NaiveDate {
+    local_day(Utc::now())
+}
+
+/// The local hour of the day (0-23) a timestamp belongs to
+pub fn local_hour(time: DateTime<Utc>) -> usize {
+    time.with_timezone(&Local).hour() as usize
+}
+
+/// Parses a local `YYYY-MM-DD HH:MM`, or a `HH:MM` on the calendar day of `reference`
+pub fn parse_time(text: &str, reference: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let naive = match NaiveDateTime::parse_from_str(text.trim(), "%Y-%m-%d %H:%M") {
+        Ok(x) => x,
+        Err(_) => reference.with_timezone(&Local).date_naive().and_time(NaiveTime::parse_from_str(text.trim(), "%H:%M").ok()?),
+    };
+
+    // Times skipped by a DST change don't exist, repeated ones take the first
+    Local.from_local_datetime(&naive).earliest().map(|x| x.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn day(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn late_work_counts_for_the_day_before() {
+        assert_eq!(day_of(at("2024-03-02 01:00"), 0), day("2024-03-02"));
+        assert_eq!(day_of(at("2024-03-02 01:00"), 4), day("2024-03-01"));
+        assert_eq!(day_of(at("2024-03-02 03:59"), 4), day("2024-03-01"));
+        assert_eq!(day_of(at("2024-03-02 04:00"), 4), day("2024-03-02"));
+        // Across the end of a month and a leap day
+        assert_eq!(day_of(at("2024-03-01 02:00"), 3), day("2024-02-29"));
+        assert_eq!(day_of(at("2024-01-01 00:30"), 1), day("2023-12-31"));
+    }
+
+    #[test]
+    fn parses_times() {
+        let reference = Local.from_local_datetime(&at("2024-05-10 12:00")).unwrap().with_timezone(&Utc);
+        let full = parse_time("2024-05-11 08:30", reference).unwrap();
+        assert_eq!(full.with_timezone(&Local).naive_local(), at("2024-05-11 08:30"));
+        let short = parse_time(" 09:15 ", reference).unwrap();
+        assert_eq!(short.with_timezone(&Local).naive_local(), at("2024-05-10 09:15"));
+        assert!(parse_time("25:00", reference).is_none());
+        assert!(parse_time("tomorrow", reference).is_none());
+    }
+}
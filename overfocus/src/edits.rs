@@ -0,0 +1,318 @@
+use std::{sync::Mutex, collections::HashMap};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc, Local};
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{storage, history::{History, Session, Outcome, AbandonReason}, tags::merge_tags};
+
+const EDITS_FILE: &str = "edits.json";
+
+/// How many corrections can be undone
+const MAX_EDITS: usize = 100;
+
+/// Serializes every read-modify-write of the edits file.<br>
+/// Corrections go along with changes to the history, so it's only ever taken while holding the history lock, never before it.
+static EDITS_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Error, Debug)]
+pub enum EditError {
+    #[error("No session matches `{0}`")]
+    NotFound(String),
+    #[error("`{0}` matches more than one session, use more of the id")]
+    Ambiguous(String),
+    #[error("A session can't end before it starts")]
+    InvalidTimes,
+    #[error("Splits have to happen between the start and the end of the session")]
+    SplitOutside,
+    #[error("Only sessions of the same stage can be merged")]
+    DifferentStages,
+    #[error("There's nothing to undo")]
+    NothingToUndo,
+}
+
+/// Fields of a session to change, the ones left as `None` stay as they are
+#[derive(Clone, Default)]
+pub struct SessionEdit {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub task: Option<Option<Uuid>>,
+    pub project: Option<Option<String>>,
+    pub tags: Option<Vec<String>>,
+}
+
+/// A correction of the history, with what's needed to revert it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Edit {
+    pub at: DateTime<Utc>,
+    pub description: String,
+    /// Sessions as they were before, for the ones that changed or went away
+    pub before: Vec<Session>,
+    /// Sessions that were changed or added
+    pub after: Vec<Uuid>,
+}
+
+/// Corrections made to the history, most recent last, persisted in the data directory
+#[derive(Default, Serialize, Deserialize)]
+pub struct EditLog {
+    pub edits: Vec<Edit>,
+}
+
+/// Finds the session whose id starts with `prefix`
+pub fn find(history: &History, prefix: &str) -> Result<Uuid> {
+    let mut matches = history.sessions.iter().filter(|x| x.id.to_string().starts_with(prefix));
+    match (matches.next(), matches.next()) {
+        (Some(session), None) if !prefix.is_empty() => Ok(session.id),
+        (Some(_), _) => Err(EditError::Ambiguous(prefix.to_string()))?,
+        (None, _) => Err(EditError::NotFound(prefix.to_string()))?,
+    }
+}
+
+/// Changes the fields of a session.<br>
+/// Moving its start or end makes the focus time match the new length, minus the time it was paused for.
+pub fn edit(id: Uuid, change: SessionEdit) -> Result<()> {
+    correct("Edit", |history| {
+        let session = history.get_mut(id).ok_or_else(|| EditError::NotFound(id.to_string()))?;
+        if change.start.is_some() || change.end.is_some() {
+            let start = change.start.unwrap_or(session.start);
+            let end = change.end.unwrap_or(session.end);
+            if end < start {
+                Err(EditError::InvalidTimes)?;
+            }
+            let paused = ((session.end - session.start).num_seconds().max(0) as u64).saturating_sub(session.focus_secs);
+            session.start = start;
+            session.end = end;
+            session.focus_secs = ((end - start).num_seconds() as u64).saturating_sub(paused);
+        }
+
+        if let Some(task) = change.task { session.task = task }
+        if let Some(project) = change.project { session.project = project }
+        if let Some(tags) = change.tags { session.tags = tags }
+        Ok(())
+    })
+}
+
+/// Splits a session in two at a point in time, returning the id of the second half
+pub fn split(id: Uuid, at: DateTime<Utc>) -> Result<Uuid> {
+    correct("Split", |history| {
+        let first = history.get_mut(id).ok_or_else(|| EditError::NotFound(id.to_string()))?;
+        if at <= first.start || at >= first.end {
+            Err(EditError::SplitOutside)?;
+        }
+
+        // Focus time is shared out by how long each half lasted
+        let total = (first.end - first.start).num_seconds() as f64;
+        let ratio = (at - first.start).num_seconds() as f64 / total;
+        let first_focus = (first.focus_secs as f64 * ratio).round() as u64;
+
+        let mut second = first.clone();
+        second.id = Uuid::new_v4();
+        second.start = at;
+        second.focus_secs = first.focus_secs - first_focus;
+        second.interruptions.retain(|x| x.at >= at);
+        second.reflection = None;
+
+        first.end = at;
+        first.focus_secs = first_focus;
+        first.interruptions.retain(|x| x.at < at);
+        // Only the second half got to the end of the stage
+        if first.outcome == Outcome::Completed {
+            first.outcome = Outcome::Abandoned(AbandonReason::Stopped);
+        }
+
+        let new = second.id;
+        let index = history.sessions.iter().position(|x| x.id == id).unwrap();
+        history.sessions.insert(index + 1, second);
+        Ok(new)
+    })
+}
+
+/// Merges two sessions of the same stage into the earliest one
+pub fn merge(a: Uuid, b: Uuid) -> Result<()> {
+    correct("Merge", |history| {
+        let (Some(x), Some(y)) = (history.get(a), history.get(b)) else {
+            Err(EditError::NotFound((if history.get(a).is_none() { a } else { b }).to_string()))?
+        };
+        if x.stage != y.stage {
+            Err(EditError::DifferentStages)?;
+        }
+        let (first, second) = if x.start <= y.start { (x.id, y.id) } else { (y.id, x.id) };
+
+        let second = history.sessions.remove(history.sessions.iter().position(|x| x.id == second).unwrap());
+        let first = history.get_mut(first).unwrap();
+        first.end = first.end.max(second.end);
+        first.focus_secs += second.focus_secs;
+        // Either one getting to the end of the stage is a completed pomodoro, so it isn't lost to a later stop or skip
+        if first.outcome != Outcome::Completed {
+            first.outcome = second.outcome;
+        }
+        first.interruptions.extend(second.interruptions);
+        first.task = first.task.or(second.task);
+        first.project = first.project.take().or(second.project);
+        first.reflection = first.reflection.take().or(second.reflection);
+        merge_tags(&mut first.tags, &second.tags);
+        Ok(())
+    })
+}
+
+pub fn delete(id: Uuid) -> Result<()> {
+    correct("Delete", |history| {
+        let len = history.sessions.len();
+        history.sessions.retain(|x| x.id != id);
+        if history.sessions.len() == len {
+            Err(EditError::NotFound(id.to_string()))?;
+        }
+        Ok(())
+    })
+}
+
+/// Reverts the last correction, returning its description
+pub fn undo() -> Result<String> {
+    History::try_update(|history| {
+        let _lock = EDITS_LOCK.lock().unwrap();
+        let mut log: EditLog = storage::load(EDITS_FILE)?;
+        let edit = log.edits.pop().ok_or(EditError::NothingToUndo)?;
+
+        history.sessions.retain(|x| !edit.after.contains(&x.id));
+        history.sessions.extend(edit.before);
+        history.sessions.sort_by_key(|x| x.start);
+        // Saved first, as an undo that can't be done again is better than one that brings sessions back twice
+        storage::save(EDITS_FILE, &log)?;
+        Ok(edit.description)
+    })
+}
+
+/// Drops the corrections that touched any of the sessions, since they can't be undone anymore.<br>
+/// Meant to be called while the history is being updated.
+pub(crate) fn forget(ids: &[Uuid]) -> Result<()> {
+    let _lock = EDITS_LOCK.lock().unwrap();
+    let mut log: EditLog = storage::load(EDITS_FILE)?;
+    let len = log.edits.len();
+    log.edits.retain(|x| !x.after.iter().chain(x.before.iter().map(|x| &x.id)).any(|x| ids.contains(x)));
+    if log.edits.len() != len {
+        storage::save(EDITS_FILE, &log)?;
+    }
+    Ok(())
+}
+
+impl EditLog {
+    pub fn load() -> Result<Self> {
+        let _lock = EDITS_LOCK.lock().unwrap();
+        storage::load(EDITS_FILE)
+    }
+}
+
+/// Applies a change to the history and logs what it replaced, so it can be undone
+fn correct<T>(description: &str, func: impl FnOnce(&mut History) -> Result<T>) -> Result<T> {
+    History::try_update(|history| {
+        let old = history.sessions.clone();
+        let res = func(history)?;
+
+        // Sessions with the same id are compared through their json, which covers every field
+        let old_ids: HashMap<Uuid, &Session> = old.iter().map(|x| (x.id, x)).collect();
+        let new_ids: HashMap<Uuid, &Session> = history.sessions.iter().map(|x| (x.id, x)).collect();
+        let same = |a: &Session, b: Option<&&Session>| b.map_or(false, |b| serde_json::to_value(a).ok() == serde_json::to_value(b).ok());
+        let before: Vec<Session> = old.iter().filter(|x| !same(x, new_ids.get(&x.id))).cloned().collect();
+        let after = history.sessions.iter().filter(|x| !same(x, old_ids.get(&x.id))).map(|x| x.id).collect();
+
+        log(description, before, after)?;
+        Ok(res)
+    })
+}
+
+/// Adds a change made to the history elsewhere to the edit log, so it can be undone too.<br>
+/// Meant to be called while the history is being updated.
+pub(crate) fn log(description: &str, before: Vec<Session>, after: Vec<Uuid>) -> Result<()> {
+    let description = match before.iter().map(|x| x.start).min() {
+        Some(start) => format!("{} of the session from {}", description, start.with_timezone(&Local).format("%Y-%m-%d %H:%M")),
+        None => description.to_string(),
+    };
+    let edit = Edit { at: Utc::now(), description, before, after };
+    let _lock = EDITS_LOCK.lock().unwrap();
+    let mut log: EditLog = storage::load(EDITS_FILE)?;
+    log.edits.push(edit);
+    if log.edits.len() > MAX_EDITS {
+        log.edits.remove(0);
+    }
+    storage::save(EDITS_FILE, &log)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::{pomodoro::PomodoroStage, testing::with_data_dir};
+
+    fn at(mins: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap() + Duration::minutes(mins)
+    }
+
+    /// Records a work session between two minutes past 9:00, focused all along
+    fn record(start: i64, end: i64, outcome: Outcome) -> Uuid {
+        let id = Uuid::new_v4();
+        History::record(Session {
+            id, stage: PomodoroStage::Work, start: at(start), end: at(end), focus_secs: (end - start) as u64 * 60, outcome,
+            interruptions: Vec::new(), task: None, reflection: None, project: None, tags: Vec::new(),
+        }).unwrap();
+        id
+    }
+
+    fn session(id: Uuid) -> Session {
+        History::load().unwrap().get(id).unwrap().clone()
+    }
+
+    #[test]
+    fn splits_focus_by_length() {
+        with_data_dir(|| {
+            let id = record(0, 30, Outcome::Completed);
+            let second = split(id, at(10)).unwrap();
+
+            let (first, second) = (session(id), session(second));
+            assert_eq!((first.end, first.focus_secs, first.outcome), (at(10), 600, Outcome::Abandoned(AbandonReason::Stopped)));
+            assert_eq!((second.start, second.focus_secs, second.outcome), (at(10), 1200, Outcome::Completed));
+            assert!(matches!(split(id, at(20)).err().unwrap().downcast_ref(), Some(EditError::SplitOutside)));
+        });
+    }
+
+    #[test]
+    fn merges_into_the_earliest_keeping_completion() {
+        with_data_dir(|| {
+            let first = record(0, 25, Outcome::Completed);
+            let second = record(30, 35, Outcome::Abandoned(AbandonReason::Stopped));
+            merge(second, first).unwrap();
+
+            let history = History::load().unwrap();
+            assert_eq!(history.sessions.len(), 1);
+            let merged = session(first);
+            assert_eq!((merged.end, merged.focus_secs, merged.outcome), (at(35), 30 * 60, Outcome::Completed));
+
+            let later = record(40, 45, Outcome::Completed);
+            let earlier = record(-10, -5, Outcome::Abandoned(AbandonReason::Stopped));
+            merge(earlier, later).unwrap();
+            assert_eq!(session(earlier).outcome, Outcome::Completed);
+        });
+    }
+
+    #[test]
+    fn undoes_corrections_in_reverse() {
+        with_data_dir(|| {
+            let kept = record(0, 25, Outcome::Completed);
+            let deleted = record(30, 55, Outcome::Completed);
+            let half = split(kept, at(10)).unwrap();
+            delete(deleted).unwrap();
+            assert!(matches!(delete(deleted).err().unwrap().downcast_ref(), Some(EditError::NotFound(_))));
+            assert_eq!(History::load().unwrap().sessions.len(), 2);
+
+            assert!(undo().unwrap().starts_with("Delete"));
+            assert_eq!(session(deleted).focus_secs, 25 * 60);
+            assert!(undo().unwrap().starts_with("Split"));
+            let history = History::load().unwrap();
+            assert!(history.get(half).is_none());
+            assert_eq!((session(kept).end, session(kept).outcome), (at(25), Outcome::Completed));
+            assert!(matches!(undo().err().unwrap().downcast_ref(), Some(EditError::NothingToUndo)));
+        });
+    }
+}
@@ -0,0 +1,309 @@
+use std::{fs::{self, OpenOptions}, io::Write, path::{Path, PathBuf}};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{storage, pomodoro::PomodoroStage, history::{History, Session, Outcome, AbandonReason, Interruption, Reflection, HISTORY_FILE}};
+
+const EVENTS_FILE: &str = "history.jsonl";
+
+/// How long the log can get before it's folded into the history snapshot
+const COMPACT_AFTER: usize = 500;
+
+#[derive(Error, Debug)]
+pub enum EventError {
+    #[error("Line {0} of the event log is corrupt: {1}")]
+    Corrupt(usize, serde_json::Error),
+}
+
+/// Something that happened to the pomodoro clock or the history, appended to the event log
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    StageStarted { id: Uuid, stage: PomodoroStage, at: DateTime<Utc>, task: Option<Uuid>, project: Option<String>, tags: Vec<String> },
+    Paused { at: DateTime<Utc> },
+    Resumed { at: DateTime<Utc> },
+    Interrupted { interruption: Interruption },
+    StageFinished { session: Session },
+    /// Credits live in the task list, the event only records when they happened
+    TaskCredited { task: Uuid, session: Uuid },
+    Reflected { session: Uuid, reflection: Reflection },
+}
+
+/// A line of the event log
+#[derive(Clone, Serialize, Deserialize)]
+struct Entry {
+    /// Increases with every event, so the ones already in the snapshot can be told apart
+    seq: u64,
+    #[serde(flatten)]
+    event: Event,
+}
+
+/// The state rebuilt from the history snapshot and the events logged after it
+pub struct Replay {
+    pub history: History,
+    /// The stage that started but hasn't finished, as far as the log goes
+    pub current: Option<Session>,
+    paused_at: Option<DateTime<Utc>>,
+    /// Events of the current stage, which survive compactions with the numbers they were logged with
+    pending: Vec<Entry>,
+    last_seq: u64,
+    /// Data directory the log and the snapshot are in
+    dir: PathBuf,
+}
+
+impl Replay {
+    /// Loads the snapshot and applies every event that isn't in it yet
+    pub(crate) fn load() -> Result<Self> {
+        Self::load_from(&storage::data_dir()?)
+    }
+
+    fn load_from(dir: &Path) -> Result<Self> {
+        let history: History = storage::load_from(&dir.join(HISTORY_FILE))?;
+        let last_seq = history.last_event;
+        let mut res = Self { history, current: None, paused_at: None, pending: Vec::new(), last_seq, dir: dir.to_path_buf() };
+
+        for entry in read(dir)?.0 {
+            if entry.seq > res.last_seq {
+                res.last_seq = entry.seq;
+                res.apply(entry);
+            }
+        }
+        Ok(res)
+    }
+
+    fn apply(&mut self, entry: Entry) {
+        match &entry.event {
+            Event::StageStarted { id, stage, at, task, project, tags } => {
+                self.current = Some(Session {
+                    id: *id, stage: *stage, start: *at, end: *at, focus_secs: 0,
+                    // Whatever never finished was stopped, one way or another
+                    outcome: Outcome::Abandoned(AbandonReason::Stopped),
+                    interruptions: Vec::new(), task: *task, reflection: None, project: project.clone(), tags: tags.clone(),
+                });
+                self.paused_at = None;
+                self.pending.clear();
+            },
+            Event::Paused { at } => {
+                self.advance(*at);
+                self.paused_at = self.current.as_ref().map(|_| *at);
+            },
+            Event::Resumed { at } => {
+                if let (Some(current), Some(_)) = (&mut self.current, self.paused_at.take()) {
+                    current.end = *at;
+                }
+            },
+            Event::Interrupted { interruption } => {
+                self.advance(interruption.at);
+                if let Some(current) = &mut self.current {
+                    current.interruptions.push(interruption.clone());
+                }
+            },
+            Event::StageFinished { session } => {
+                if self.current.as_ref().map_or(false, |x| x.id == session.id) {
+                    self.current = None;
+                    self.pending.clear();
+                }
+                match self.history.get_mut(session.id) {
+                    Some(old) => *old = session.clone(),
+                    None => self.history.sessions.push(session.clone()),
+                }
+            },
+            Event::TaskCredited { .. } => {},
+            Event::Reflected { session, reflection } => {
+                if let Some(session) = self.history.get_mut(*session) {
+                    session.reflection = Some(reflection.clone());
+                }
+            },
+        }
+
+        if self.current.is_some() && !matches!(entry.event, Event::StageFinished { .. } | Event::TaskCredited { .. } | Event::Reflected { .. }) {
+            self.pending.push(entry);
+        }
+    }
+
+    /// Moves the end of the current stage up to `at`, counting the time as focus unless paused
+    fn advance(&mut self, at: DateTime<Utc>) {
+        let Some(current) = &mut self.current else { return };
+        if self.paused_at.is_none() {
+            current.focus_secs += (at - current.end).num_seconds().max(0) as u64;
+        }
+        current.end = current.end.max(at);
+    }
+
+    /// Folds the log into the history snapshot, keeping only the events of the current stage.<br>
+    /// The snapshot holds everything up to the first of them, so a crash before the log is rewritten
+    /// still replays the current stage from the old log.
+    pub(crate) fn compact(&mut self) -> Result<()> {
+        self.history.last_event = self.pending.first().map_or(self.last_seq, |x| x.seq - 1);
+        storage::save_to(&self.dir.join(HISTORY_FILE), &self.history)?;
+
+        let mut text = String::new();
+        for entry in &self.pending {
+            text += &serde_json::to_string(entry)?;
+            text.push('\n');
+        }
+        let path = self.dir.join(EVENTS_FILE);
+        let tmp = path.with_extension("jsonl.tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Appends an event to the log, flushed to disk before returning.<br>
+/// Compacts the log when it gets too long.
+pub(crate) fn append(event: Event) -> Result<()> {
+    append_to(&storage::data_dir()?, event)
+}
+
+fn append_to(dir: &Path, event: Event) -> Result<()> {
+    let (entries, complete) = read(dir)?;
+    let seq = match entries.last() {
+        Some(entry) => entry.seq,
+        None => storage::load_from::<History>(&dir.join(HISTORY_FILE))?.last_event,
+    } + 1;
+
+    let mut file = OpenOptions::new().create(true).append(true).open(dir.join(EVENTS_FILE))?;
+    // Drops what a crash left of a line, so it doesn't get glued to this one
+    if file.metadata()?.len() > complete {
+        file.set_len(complete)?;
+    }
+    file.write_all(format!("{}\n", serde_json::to_string(&Entry { seq, event })?).as_bytes())?;
+    file.sync_data()?;
+
+    if entries.len() + 1 > COMPACT_AFTER {
+        Replay::load_from(dir)?.compact()?;
+    }
+    Ok(())
+}
+
+/// Reads every entry of the log, along with the length of its complete lines.<br>
+/// A last line cut short by a crash is left out, anything else that doesn't parse is an error.
+fn read(dir: &Path) -> Result<(Vec<Entry>, u64)> {
+    let path = dir.join(EVENTS_FILE);
+    if !path.exists() {
+        return Ok((Vec::new(), 0));
+    }
+
+    let text = fs::read_to_string(path)?;
+    let complete = text.rfind('\n').map_or(0, |x| x + 1);
+    let mut res = Vec::new();
+    for (i, line) in text[..complete].lines().enumerate().filter(|(_, x)| !x.trim().is_empty()) {
+        res.push(serde_json::from_str(line).map_err(|e| EventError::Corrupt(i + 1, e))?);
+    }
+    Ok((res, complete as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::testing::temp_dir;
+
+    fn at(mins: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap() + Duration::minutes(mins)
+    }
+
+    fn started(id: Uuid, mins: i64) -> Event {
+        Event::StageStarted { id, stage: PomodoroStage::Work, at: at(mins), task: None, project: None, tags: Vec::new() }
+    }
+
+    fn finished(id: Uuid, mins: i64) -> Event {
+        Event::StageFinished { session: Session {
+            id, stage: PomodoroStage::Work, start: at(0), end: at(mins), focus_secs: mins as u64 * 60, outcome: Outcome::Completed,
+            interruptions: Vec::new(), task: None, reflection: None, project: None, tags: Vec::new(),
+        }}
+    }
+
+    fn seqs(dir: &Path) -> Vec<u64> {
+        read(dir).unwrap().0.iter().map(|x| x.seq).collect()
+    }
+
+    #[test]
+    fn replays_finished_and_running_stages() {
+        let dir = temp_dir();
+        let (done, running) = (Uuid::new_v4(), Uuid::new_v4());
+        for event in [started(done, 0), finished(done, 25), started(running, 30), Event::Paused { at: at(40) }, Event::Resumed { at: at(50) }, Event::Paused { at: at(55) }] {
+            append_to(&dir, event).unwrap();
+        }
+
+        let replay = Replay::load_from(&dir).unwrap();
+        assert_eq!(replay.history.sessions.len(), 1);
+        assert_eq!(replay.history.sessions[0].id, done);
+        // The time paused doesn't count as focus
+        let current = replay.current.unwrap();
+        assert_eq!((current.id, current.focus_secs, current.end), (running, 15 * 60, at(55)));
+        assert_eq!(seqs(&dir), [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn compaction_keeps_the_running_stage_and_the_numbering() {
+        let dir = temp_dir();
+        let (done, running) = (Uuid::new_v4(), Uuid::new_v4());
+        for event in [started(done, 0), finished(done, 25), started(running, 30), Event::Paused { at: at(40) }] {
+            append_to(&dir, event).unwrap();
+        }
+
+        Replay::load_from(&dir).unwrap().compact().unwrap();
+        let snapshot: History = storage::load_from(&dir.join(HISTORY_FILE)).unwrap();
+        assert_eq!((snapshot.sessions.len(), snapshot.last_event), (1, 2));
+        assert_eq!(seqs(&dir), [3, 4]);
+
+        append_to(&dir, Event::Resumed { at: at(45) }).unwrap();
+        append_to(&dir, finished(running, 60)).unwrap();
+        assert_eq!(seqs(&dir), [3, 4, 5, 6]);
+        let replay = Replay::load_from(&dir).unwrap();
+        assert!(replay.current.is_none());
+        assert_eq!(replay.history.sessions.len(), 2);
+    }
+
+    #[test]
+    fn crash_during_compaction_keeps_the_running_stage() {
+        let dir = temp_dir();
+        let (done, running) = (Uuid::new_v4(), Uuid::new_v4());
+        for event in [started(done, 0), finished(done, 25), started(running, 30)] {
+            append_to(&dir, event).unwrap();
+        }
+
+        // The snapshot gets written but the log doesn't
+        let log = fs::read_to_string(dir.join(EVENTS_FILE)).unwrap();
+        Replay::load_from(&dir).unwrap().compact().unwrap();
+        fs::write(dir.join(EVENTS_FILE), log).unwrap();
+
+        let replay = Replay::load_from(&dir).unwrap();
+        assert_eq!(replay.history.sessions.len(), 1);
+        assert_eq!(replay.current.unwrap().id, running);
+    }
+
+    #[test]
+    fn drops_a_torn_last_line() {
+        let dir = temp_dir();
+        let id = Uuid::new_v4();
+        append_to(&dir, started(id, 0)).unwrap();
+        let mut file = OpenOptions::new().append(true).open(dir.join(EVENTS_FILE)).unwrap();
+        file.write_all(br#"{"seq":2,"event":"stage_fini"#).unwrap();
+
+        assert_eq!(seqs(&dir), [1]);
+        append_to(&dir, finished(id, 25)).unwrap();
+        assert_eq!(seqs(&dir), [1, 2]);
+        assert_eq!(Replay::load_from(&dir).unwrap().history.sessions.len(), 1);
+    }
+
+    #[test]
+    fn refuses_corrupt_lines() {
+        let dir = temp_dir();
+        let id = Uuid::new_v4();
+        append_to(&dir, started(id, 0)).unwrap();
+        let mut file = OpenOptions::new().append(true).open(dir.join(EVENTS_FILE)).unwrap();
+        file.write_all(b"not an event\n").unwrap();
+        append_to(&dir, finished(id, 25)).err().unwrap();
+
+        let err = Replay::load_from(&dir).err().unwrap();
+        assert!(matches!(err.downcast_ref::<EventError>(), Some(EventError::Corrupt(2, _))));
+    }
+}
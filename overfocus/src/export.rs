@@ -0,0 +1,376 @@
+use std::{str::FromStr, path::PathBuf, fs};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc, Local};
+use serde_json::json;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{storage, history::{History, Session, Outcome}, tasks::TaskList, stats::DateRange, days::local_day, pomodoro::PomodoroStage};
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("Unknown export format `{0}`")]
+    UnknownFormat(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    /// iCalendar, with a VEVENT for each work stage
+    Ical,
+    /// JSON that `timew import` understands
+    Timewarrior,
+    /// Org-mode `CLOCK:` lines under a heading per task
+    Org,
+}
+
+/// Which sessions get exported
+#[derive(Clone, Default)]
+pub struct ExportFilter {
+    pub range: Option<DateRange>,
+    /// Sessions must have every one of these tags
+    pub tags: Vec<String>,
+}
+
+/// Writes the filtered sessions of the history in the given format
+pub fn export(history: &History, tasks: &TaskList, format: ExportFormat, filter: &ExportFilter) -> Result<String> {
+    let sessions: Vec<&Session> = history.sessions.iter().filter(|x| filter.matches(x)).collect();
+    match format {
+        ExportFormat::Csv => Ok(to_csv(&sessions, tasks)),
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(&sessions)? + "\n"),
+        ExportFormat::Ical => Ok(to_ical(&sessions, tasks)),
+        ExportFormat::Timewarrior => Ok(to_timewarrior(&sessions, tasks)? + "\n"),
+        ExportFormat::Org => Ok(to_org(&sessions, tasks)),
+    }
+}
+
+/// Exports the persisted history into the `exports` folder of the data directory, returning the file path
+pub fn export_to_data_dir(format: ExportFormat, filter: &ExportFilter) -> Result<PathBuf> {
+    let text = export(&History::load()?, &TaskList::load()?, format, filter)?;
+
+    let dir = storage::data_dir()?.join("exports");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("overfocus-{}.{}", Local::now().format("%Y%m%d-%H%M%S"), format.extension()));
+    fs::write(&path, text)?;
+    Ok(path)
+}
+
+impl ExportFilter {
+    pub fn matches(&self, session: &Session) -> bool {
+        self.range.map_or(true, |x| x.contains(local_day(session.start)))
+            && self.tags.iter().all(|tag| session.tags.contains(tag))
+    }
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 5] = [ExportFormat::Csv, ExportFormat::Json, ExportFormat::Ical, ExportFormat::Timewarrior, ExportFormat::Org];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ical => "ics",
+            ExportFormat::Timewarrior => "timew.json",
+            ExportFormat::Org => "org",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Json => "JSON",
+            ExportFormat::Ical => "iCalendar",
+            ExportFormat::Timewarrior => "Timewarrior",
+            ExportFormat::Org => "Org-mode",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "ics" | "ical" => Ok(ExportFormat::Ical),
+            "timew" | "timewarrior" => Ok(ExportFormat::Timewarrior),
+            "org" => Ok(ExportFormat::Org),
+            _ => Err(ExportError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// Title of the task a session was for, if it still exists
+pub(crate) fn task_title<'a>(session: &Session, tasks: &'a TaskList) -> Option<&'a str> {
+    session.task.and_then(|id| tasks.get(id)).map(|x| x.title.as_str())
+}
+
+fn display_stage(stage: PomodoroStage) -> &'static str {
+    match stage {
+        PomodoroStage::Work => "work",
+        PomodoroStage::ShortBreak => "short break",
+        PomodoroStage::LongBreak => "long break",
+    }
+}
+
+
+
+// · · ·  CSV  · · · //
+
+fn to_csv(sessions: &[&Session], tasks: &TaskList) -> String {
+    let mut res = String::from("id,stage,start,end,focus_minutes,outcome,task,project,tags,interruptions,rating,note\n");
+    for session in sessions {
+        let outcome = match &session.outcome {
+            Outcome::Completed => "completed".to_string(),
+            Outcome::Abandoned(reason) => format!("abandoned: {}", reason),
+        };
+        let fields = [
+            session.id.to_string(),
+            display_stage(session.stage).to_string(),
+            session.start.to_rfc3339(),
+            session.end.to_rfc3339(),
+            format!("{:.1}", session.focus_secs as f64 / 60.0),
+            outcome,
+            task_title(session, tasks).unwrap_or_default().to_string(),
+            session.project.clone().unwrap_or_default(),
+            session.tags.join(" "),
+            session.interruptions.len().to_string(),
+            session.reflection.as_ref().map(|x| x.rating.to_string()).unwrap_or_default(),
+            session.reflection.as_ref().and_then(|x| x.note.clone()).unwrap_or_default(),
+        ];
+
+        let line: Vec<String> = fields.iter().map(|x| csv_field(x)).collect();
+        res.push_str(&line.join(","));
+        res.push('\n');
+    }
+    res
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+
+
+// · · ·  iCalendar  · · · //
+
+fn to_ical(sessions: &[&Session], tasks: &TaskList) -> String {
+    let mut res = String::new();
+    ical_line(&mut res, "BEGIN:VCALENDAR");
+    ical_line(&mut res, "VERSION:2.0");
+    ical_line(&mut res, "PRODID:-//overfocus//overfocus//EN");
+
+    let stamp = ical_time(Utc::now());
+    for session in sessions.iter().filter(|x| x.stage == PomodoroStage::Work) {
+        let summary = match task_title(session, tasks) {
+            Some(title) => format!("Pomodoro: {}", title),
+            None => "Pomodoro".to_string(),
+        };
+
+        ical_line(&mut res, "BEGIN:VEVENT");
+        ical_line(&mut res, &format!("UID:{}@overfocus", session.id));
+        ical_line(&mut res, &format!("DTSTAMP:{}", stamp));
+        ical_line(&mut res, &format!("DTSTART:{}", ical_time(session.start)));
+        ical_line(&mut res, &format!("DTEND:{}", ical_time(session.end)));
+        ical_line(&mut res, &format!("SUMMARY:{}", ical_escape(&summary)));
+        if !session.tags.is_empty() {
+            let tags: Vec<String> = session.tags.iter().map(|x| ical_escape(x)).collect();
+            ical_line(&mut res, &format!("CATEGORIES:{}", tags.join(",")));
+        }
+        if let Some(note) = session.reflection.as_ref().and_then(|x| x.note.as_ref()) {
+            ical_line(&mut res, &format!("DESCRIPTION:{}", ical_escape(note)));
+        }
+        if let Outcome::Abandoned(reason) = &session.outcome {
+            ical_line(&mut res, &format!("COMMENT:Abandoned ({})", reason));
+        }
+        ical_line(&mut res, "END:VEVENT");
+    }
+
+    ical_line(&mut res, "END:VCALENDAR");
+    res
+}
+
+fn ical_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn ical_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+}
+
+/// Writes a content line, folding it every 75 bytes as the spec asks
+fn ical_line(res: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            res.push_str("\r\n ");
+            width = 1;
+        }
+        width += c.len_utf8();
+        res.push(c);
+    }
+    res.push_str("\r\n");
+}
+
+
+
+// · · ·  Timewarrior  · · · //
+
+/// Work stages as Timewarrior intervals, tagged with their tags, project and task
+fn to_timewarrior(sessions: &[&Session], tasks: &TaskList) -> Result<String> {
+    let intervals: Vec<serde_json::Value> = sessions.iter().filter(|x| x.stage == PomodoroStage::Work).map(|session| {
+        let mut tags = session.tags.clone();
+        tags.extend(session.project.clone());
+        tags.extend(task_title(session, tasks).map(String::from));
+
+        let mut interval = json!({
+            "start": ical_time(session.start),
+            "end": ical_time(session.end),
+            "tags": tags,
+        });
+        if let Some(note) = session.reflection.as_ref().and_then(|x| x.note.as_ref()) {
+            interval["annotation"] = json!(note);
+        }
+        interval
+    }).collect();
+
+    Ok(serde_json::to_string_pretty(&intervals)?)
+}
+
+
+
+// · · ·  Org-mode  · · · //
+
+/// Work stages as org `CLOCK:` lines, in a logbook under a heading per task
+fn to_org(sessions: &[&Session], tasks: &TaskList) -> String {
+    // Groups keep the order in which tasks were first worked on
+    let mut groups: Vec<(Option<Uuid>, Vec<&Session>)> = Vec::new();
+    for session in sessions.iter().filter(|x| x.stage == PomodoroStage::Work) {
+        match groups.iter_mut().find(|(task, _)| *task == session.task) {
+            Some((_, group)) => group.push(session),
+            None => groups.push((session.task, vec![session])),
+        }
+    }
+
+    let mut res = String::new();
+    for (id, group) in groups {
+        let task = id.and_then(|id| tasks.get(id));
+        let title = task.map(|x| x.title.as_str()).unwrap_or("Pomodoros");
+        let tags = task.map(|x| x.tags.as_slice()).unwrap_or_default();
+
+        res.push_str(&format!("* {}", title));
+        if !tags.is_empty() {
+            let tags: Vec<String> = tags.iter().map(|x| org_tag(x)).collect();
+            res.push_str(&format!(" :{}:", tags.join(":")));
+        }
+        res.push('\n');
+
+        res.push_str("  :LOGBOOK:\n");
+        for session in group.iter().rev() {
+            // Org takes the duration from the timestamps, so the clock ends once the focus time is up instead of at the end
+            // of the span, leaving pauses out as `focus_secs` does elsewhere
+            let minutes = session.focus_secs / 60;
+            let end = session.start + chrono::Duration::minutes(minutes as i64);
+            res.push_str(&format!("  CLOCK: {}--{} => {:>2}:{:02}\n", org_time(session.start), org_time(end), minutes / 60, minutes % 60));
+        }
+        res.push_str("  :END:\n");
+    }
+    res
+}
+
+fn org_time(time: DateTime<Utc>) -> String {
+    time.with_timezone(&Local).format("[%Y-%m-%d %a %H:%M]").to_string()
+}
+
+/// Org tags only take letters, digits and `_@#%`, anything else becomes `_`
+fn org_tag(tag: &str) -> String {
+    tag.chars().map(|c| if c.is_alphanumeric() || "_@#%".contains(c) { c } else { '_' }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::history::{AbandonReason, Reflection};
+
+    fn session(task: Option<Uuid>, tags: &[&str]) -> Session {
+        let start = Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap();
+        Session {
+            id: Uuid::new_v4(), stage: PomodoroStage::Work, start, end: start + Duration::minutes(30), focus_secs: 25 * 60, outcome: Outcome::Completed,
+            interruptions: Vec::new(), task, reflection: None, project: None, tags: tags.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    fn export_all(sessions: Vec<Session>, tasks: &TaskList, format: ExportFormat) -> String {
+        export(&History { sessions, ..Default::default() }, tasks, format, &ExportFilter::default()).unwrap()
+    }
+
+    #[test]
+    fn csv_quotes_fields_that_need_it() {
+        let mut session = session(None, &["a", "b"]);
+        session.project = Some("Client, Inc".to_string());
+        session.reflection = Some(Reflection { rating: 4, note: Some("Said \"done\"\nthen left".to_string()) });
+
+        let csv = export_all(vec![session], &TaskList::default(), ExportFormat::Csv);
+        let row = csv.lines().skip(1).collect::<Vec<_>>().join("\n");
+        assert!(row.contains(",25.0,completed,,\"Client, Inc\",a b,0,4,\"Said \"\"done\"\"\nthen left\""));
+    }
+
+    #[test]
+    fn ical_escapes_and_folds_lines() {
+        let mut tasks = TaskList::default();
+        let task = tasks.add(format!("Write; edit, review {}", "and more ".repeat(10)), 1);
+        let mut stopped = session(Some(task), &["deep,work"]);
+        stopped.outcome = Outcome::Abandoned(AbandonReason::Stopped);
+        let mut on_break = session(None, &[]);
+        on_break.stage = PomodoroStage::ShortBreak;
+
+        let ical = export_all(vec![stopped, on_break], &tasks, ExportFormat::Ical);
+        assert!(ical.lines().all(|x| x.trim_end_matches('\r').len() <= 75));
+        let unfolded = ical.replace("\r\n ", "");
+        assert!(unfolded.contains("SUMMARY:Pomodoro: Write\\; edit\\, review and more"));
+        assert!(unfolded.contains("CATEGORIES:deep\\,work"));
+        assert!(unfolded.contains("COMMENT:Abandoned (stopped)"));
+        assert_eq!(unfolded.matches("BEGIN:VEVENT").count(), 1);
+    }
+
+    #[test]
+    fn timewarrior_tags_work_with_its_project_and_task() {
+        let mut tasks = TaskList::default();
+        let task = tasks.add("Write \"intro\"".to_string(), 1);
+        let mut session = session(Some(task), &["deep work"]);
+        session.project = Some("book".to_string());
+        session.reflection = Some(Reflection { rating: 3, note: Some("Slow start".to_string()) });
+
+        let json: serde_json::Value = serde_json::from_str(&export_all(vec![session], &tasks, ExportFormat::Timewarrior)).unwrap();
+        assert_eq!(json[0]["start"], "20230501T090000Z");
+        assert_eq!(json[0]["end"], "20230501T093000Z");
+        assert_eq!(json[0]["tags"], json!(["deep work", "book", "Write \"intro\""]));
+        assert_eq!(json[0]["annotation"], "Slow start");
+    }
+
+    #[test]
+    fn org_groups_by_task_with_valid_tags() {
+        let mut tasks = TaskList::default();
+        let task = tasks.add("Write".to_string(), 1);
+        tasks.get_mut(task).unwrap().tags = vec!["deep-work".to_string(), "client x".to_string(), "@home".to_string()];
+        let sessions = vec![session(Some(task), &[]), session(None, &[]), session(Some(task), &[])];
+
+        let org = export_all(sessions, &tasks, ExportFormat::Org);
+        let headings: Vec<&str> = org.lines().filter(|x| x.starts_with('*')).collect();
+        assert_eq!(headings, ["* Write :deep_work:client_x:@home:", "* Pomodoros"]);
+        // Clocked for the focus time rather than the whole span
+        assert_eq!(org.matches("=>  0:25").count(), 3);
+        let clock = org.lines().find(|x| x.trim_start().starts_with("CLOCK:")).unwrap();
+        let start = session(None, &[]).start;
+        assert!(clock.contains(&format!("{}--{}", org_time(start), org_time(start + Duration::minutes(25)))));
+    }
+}
@@ -0,0 +1,139 @@
+use chrono::{NaiveDate, Datelike, Duration};
+use serde::{Serialize, Deserialize};
+
+use crate::{history::History, stats::{self, DateRange}, config::Config};
+
+/// Pomodoros to complete on each day of the week, Monday first.<br>
+/// A goal of 0 makes that day a rest day.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyGoals {
+    pub weekdays: [u8; 7],
+}
+
+/// How a day went against the goal it has
+#[derive(Clone, Copy, Default)]
+pub struct GoalDay {
+    pub goal: u8,
+    pub done: usize,
+}
+
+/// Progress of a day towards a goal, counted from the history so it never drifts from what was recorded
+pub fn progress(history: &History, goals: &DailyGoals, day: NaiveDate) -> GoalDay {
+    GoalDay { goal: goals.target(day), done: stats::totals(history, DateRange { from: day, to: day }).pomodoros }
+}
+
+/// Consecutive days meeting the goals, or with any work while none are set, ending today.<br>
+/// Rest days, be it from the config or from a goal of 0, and vacations don't break the streak,
+/// and neither does today until it's over.
+pub fn streak(history: &History, goals: &DailyGoals, config: &Config, today: NaiveDate) -> usize {
+    let done = stats::pomodoros_per_day(history);
+
+    // Whether a day counts for the streak, `None` for days that are skipped
+    let met = |day: NaiveDate| -> Option<bool> {
+        let day_goal = GoalDay { goal: goals.target(day), done: done.get(&day).copied().unwrap_or(0) };
+        let reached = match goals.is_set() {
+            true => day_goal.reached(),
+            false => day_goal.done > 0,
+        };
+        match reached {
+            true => Some(true),
+            // Work on a rest day is a bonus
+            false if config.on_vacation(day) || config.is_rest_day(day) || (goals.is_set() && day_goal.goal == 0) => None,
+            false => Some(false),
+        }
+    };
+
+    let Some(first) = done.keys().next().copied() else { return 0 };
+    let mut day = if met(today) == Some(true) { today } else { today - Duration::days(1) };
+    let mut res = 0;
+    while day >= first {
+        match met(day) {
+            Some(true) => res += 1,
+            Some(false) => break,
+            None => {},
+        }
+        day = day - Duration::days(1);
+    }
+    res
+}
+
+impl DailyGoals {
+    /// The goal of the weekday a date falls on
+    pub fn target(&self, day: NaiveDate) -> u8 {
+        self.weekdays[day.weekday().num_days_from_monday() as usize]
+    }
+
+    /// Whether any day has a goal at all
+    pub fn is_set(&self) -> bool {
+        self.weekdays.iter().any(|x| *x > 0)
+    }
+}
+
+impl GoalDay {
+    pub fn reached(&self) -> bool {
+        self.goal > 0 && self.done >= self.goal as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Weekday, Local, TimeZone, Utc};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{history::{Session, Outcome}, pomodoro::PomodoroStage};
+
+    fn day(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    /// History with the given amount of pomodoros at noon of each day
+    fn history(days: &[(&str, usize)]) -> History {
+        let sessions = days.iter().flat_map(|(x, pomodoros)| {
+            let start = Local.from_local_datetime(&day(x).and_hms_opt(12, 0, 0).unwrap()).unwrap().with_timezone(&Utc);
+            (0..*pomodoros).map(move |_| Session {
+                id: Uuid::new_v4(), stage: PomodoroStage::Work, start, end: start, focus_secs: 25 * 60, outcome: Outcome::Completed,
+                interruptions: Vec::new(), task: None, reflection: None, project: None, tags: Vec::new(),
+            })
+        }).collect();
+        History { sessions, ..Default::default() }
+    }
+
+    /// Two work weeks starting Monday 2024-01-01, with nothing done on the weekend in between
+    fn work_weeks(pomodoros: usize) -> History {
+        let days: Vec<String> = (1..=5).chain(8..=12).map(|x| format!("2024-01-{:02}", x)).collect();
+        history(&days.iter().map(|x| (x.as_str(), pomodoros)).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn weekends_break_streaks_without_rest_days() {
+        let config = Config::default();
+        assert_eq!(streak(&work_weeks(1), &DailyGoals::default(), &config, day("2024-01-12")), 5);
+    }
+
+    #[test]
+    fn rest_days_without_goals() {
+        let config = Config { rest_days: vec![Weekday::Sat, Weekday::Sun], ..Default::default() };
+        assert_eq!(streak(&work_weeks(1), &DailyGoals::default(), &config, day("2024-01-12")), 10);
+    }
+
+    #[test]
+    fn goals_and_rest_days() {
+        let goals = DailyGoals { weekdays: [4, 4, 4, 4, 4, 0, 0] };
+        let config = Config::default();
+        assert_eq!(streak(&work_weeks(4), &goals, &config, day("2024-01-12")), 10);
+        // Falling short of the goal breaks it
+        assert_eq!(streak(&work_weeks(3), &goals, &config, day("2024-01-12")), 0);
+        assert_eq!(progress(&work_weeks(3), &goals, day("2024-01-08")).done, 3);
+        assert!(!progress(&work_weeks(3), &goals, day("2024-01-08")).reached());
+    }
+
+    #[test]
+    fn today_and_vacations() {
+        let config = Config { vacations: vec![DateRange { from: day("2024-01-06"), to: day("2024-01-07") }], ..Default::default() };
+        // Today isn't over yet, so it doesn't break the streak until tomorrow
+        assert_eq!(streak(&work_weeks(1), &DailyGoals::default(), &config, day("2024-01-13")), 10);
+        assert_eq!(streak(&work_weeks(1), &DailyGoals::default(), &config, day("2024-01-14")), 0);
+        assert_eq!(streak(&History::default(), &DailyGoals::default(), &config, day("2024-01-14")), 0);
+    }
+}
@@ -0,0 +1,186 @@
+use std::{sync::Mutex, fmt::Display};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc, NaiveDate};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::{pomodoro::PomodoroStage, events::{self, Event, Replay}};
+
+pub(crate) const HISTORY_FILE: &str = "history.json";
+
+/// Serializes every read-modify-write of the history file
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+/// A single stage ran by the pomodoro clock
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: Uuid,
+    pub stage: PomodoroStage,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Seconds the clock actually ticked, pauses excluded
+    pub focus_secs: u64,
+    pub outcome: Outcome,
+    #[serde(default)]
+    pub interruptions: Vec<Interruption>,
+    /// The task this session was worked on
+    #[serde(default)]
+    pub task: Option<Uuid>,
+    #[serde(default)]
+    pub reflection: Option<Reflection>,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// How a work stage went, written down once it's over
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Reflection {
+    /// Focus rating from 1 to 5
+    pub rating: u8,
+    pub note: Option<String>,
+}
+
+/// Something that broke the focus during a work stage, logged without pausing
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Interruption {
+    pub kind: InterruptionKind,
+    pub at: DateTime<Utc>,
+    pub note: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum InterruptionKind {
+    /// Came from yourself, noted as `'`
+    Internal,
+    /// Came from someone else, noted as `-`
+    External,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Outcome {
+    Completed,
+    Abandoned(AbandonReason),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum AbandonReason {
+    /// Paused during work while in strict mode
+    Paused,
+    /// Stayed paused for longer than the strict mode grace period
+    PausedTooLong,
+    /// The clock was stopped before the stage finished
+    Stopped,
+    /// The stage was cut short to move on to the next one
+    Skipped,
+}
+
+/// Work of the pruned sessions of a day that shared a task, a project and tags
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Rollup {
+    pub day: NaiveDate,
+    pub task: Option<Uuid>,
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+    pub focus_secs: u64,
+    pub pomodoros: usize,
+    pub interruptions: usize,
+    /// Work sessions the totals were made of, so syncing can tell what two rollups have in common
+    #[serde(default)]
+    pub sessions: Vec<Uuid>,
+}
+
+/// Every session recorded so far, persisted in the data directory.<br>
+/// The file is a snapshot, sessions recorded since are in the event log until it's compacted.
+#[derive(Default, Serialize, Deserialize)]
+pub struct History {
+    pub sessions: Vec<Session>,
+    /// Daily totals of the sessions that were pruned
+    #[serde(default)]
+    pub rollups: Vec<Rollup>,
+    /// Sequence number of the last logged event the snapshot includes
+    #[serde(default)]
+    pub last_event: u64,
+}
+
+impl History {
+    pub fn load() -> Result<Self> {
+        let _lock = HISTORY_LOCK.lock().unwrap();
+        Ok(Replay::load()?.history)
+    }
+
+    /// Records the stage the event log says is still running as stopped, and returns it.<br>
+    /// Meant for starting up after the app was killed without a chance to record it.
+    pub fn recover() -> Result<Option<Session>> {
+        let _lock = HISTORY_LOCK.lock().unwrap();
+        let Some(session) = Replay::load()?.current else { return Ok(None) };
+        events::append(Event::StageFinished { session: session.clone() })?;
+        Ok(Some(session))
+    }
+
+    /// Loads the history, modifies it and saves it back in one go, compacting the event log
+    pub fn update<T>(func: impl FnOnce(&mut History) -> T) -> Result<T> {
+        Self::try_update(|history| Ok(func(history)))
+    }
+
+    /// Like `update`, but only saves when the change succeeds
+    pub fn try_update<T>(func: impl FnOnce(&mut History) -> Result<T>) -> Result<T> {
+        let _lock = HISTORY_LOCK.lock().unwrap();
+        let mut replay = Replay::load()?;
+        let res = func(&mut replay.history)?;
+        replay.compact()?;
+        Ok(res)
+    }
+
+    /// Appends an event to the log
+    pub fn log(event: Event) -> Result<()> {
+        let _lock = HISTORY_LOCK.lock().unwrap();
+        events::append(event)
+    }
+
+    /// Appends a finished session to the persisted history
+    pub fn record(session: Session) -> Result<()> {
+        Self::log(Event::StageFinished { session })
+    }
+
+    /// Stores a reflection with an already recorded session
+    pub fn reflect(id: Uuid, reflection: Reflection) -> Result<()> {
+        Self::log(Event::Reflected { session: id, reflection })
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&Session> {
+        self.sessions.iter().find(|x| x.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: Uuid) -> Option<&mut Session> {
+        self.sessions.iter_mut().find(|x| x.id == id)
+    }
+}
+
+impl Session {
+    pub fn is_completed_work(&self) -> bool {
+        matches!(self.stage, PomodoroStage::Work) && self.outcome == Outcome::Completed
+    }
+}
+
+impl Display for InterruptionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterruptionKind::Internal => write!(f, "internal"),
+            InterruptionKind::External => write!(f, "external"),
+        }
+    }
+}
+
+impl Display for AbandonReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AbandonReason::Paused => write!(f, "paused during work"),
+            AbandonReason::PausedTooLong => write!(f, "paused for too long"),
+            AbandonReason::Stopped => write!(f, "stopped"),
+            AbandonReason::Skipped => write!(f, "skipped"),
+        }
+    }
+}
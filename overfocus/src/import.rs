@@ -0,0 +1,565 @@
+use std::{path::{Path, PathBuf}, str::FromStr, fs, thread, time::Duration};
+
+use anyhow::Result;
+use chrono::{Local, Utc};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{tasks::{TaskList, Task}, log_info, unwrap_err};
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("Unknown import format `{0}`")]
+    UnknownFormat(String),
+    #[error("Couldn't tell the format of `{0}`, pass it explicitly")]
+    UndetectedFormat(PathBuf),
+    #[error("`{0}` isn't a Taskwarrior export")]
+    InvalidTaskwarrior(PathBuf),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ImportFormat {
+    TodoTxt,
+    /// The output of `task export`
+    Taskwarrior,
+    /// `- [ ]` checklists
+    Markdown,
+}
+
+/// Where an imported task came from, so completion can be written back
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskSource {
+    pub path: PathBuf,
+    pub format: ImportFormat,
+    /// Identifies the task within the file: its uuid for Taskwarrior, its `id:` tag or its text otherwise,
+    /// numbered after the first time the same one shows up
+    pub key: String,
+    /// Where the task is in the file, to follow it when its text changes.<br>
+    /// Unknown for tasks imported before it was kept, until they're imported again.
+    #[serde(default)]
+    pub line: Option<usize>,
+    /// Whether the file had the task completed when last read, to tell which side changed it since
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// What an import changed
+#[derive(Clone, Copy, Default)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub updated: usize,
+    pub written_back: usize,
+}
+
+/// A task as read from an external file
+struct ExternalTask {
+    key: String,
+    /// Line of the task, or its index in a Taskwarrior export
+    line: usize,
+    title: String,
+    done: bool,
+    priority: Option<char>,
+    project: Option<String>,
+    tags: Vec<String>,
+}
+
+/// Imports the tasks of a file into the task list.<br>
+/// Tasks completed in overfocus are marked as completed in the file, and the ones completed or reopened in the file are in overfocus.
+pub fn import(path: &Path, format: ImportFormat) -> Result<ImportSummary> {
+    let path = fs::canonicalize(path)?;
+    let text = fs::read_to_string(&path)?;
+    let external = parse(&path, &text, format)?;
+    let (added, updated, pending) = TaskList::update(|list| apply(list, &path, format, external))?;
+    let written_back = write_back(&path, &text, format, &pending)?;
+    Ok(ImportSummary { added, updated, written_back })
+}
+
+/// Brings the tasks of a file into the list, returning how many were added and updated,
+/// and the keys of the ones to complete in the file
+fn apply(list: &mut TaskList, path: &Path, format: ImportFormat, external: Vec<ExternalTask>) -> (usize, usize, Vec<String>) {
+    let keys: Vec<String> = external.iter().map(|x| x.key.clone()).collect();
+    let (mut added, mut updated) = (0, 0);
+    let mut pending = Vec::new();
+
+    for task in external {
+        let from_file = |x: &TaskSource| x.path == path;
+        // A task whose key is gone from the file but that was on the same line had its text edited
+        let existing = list.tasks.iter().position(|x| x.source.as_ref().map_or(false, |x| from_file(x) && x.key == task.key))
+            .or_else(|| list.tasks.iter().position(|x| x.source.as_ref().map_or(false, |x| from_file(x) && x.line == Some(task.line) && !keys.contains(&x.key))));
+
+        match existing {
+            Some(i) => {
+                let existing = &mut list.tasks[i];
+                let source = existing.source.as_mut().unwrap();
+                let file_changed = source.done != task.done;
+                // The file only gets completions, a task reopened in overfocus stays done there
+                if !file_changed && existing.done && !task.done {
+                    pending.push(task.key.clone());
+                }
+                *source = TaskSource { path: path.to_path_buf(), format, key: task.key.clone(), line: Some(task.line), done: task.done };
+                if merge(existing, task, file_changed) {
+                    updated += 1;
+                }
+            },
+            None => {
+                let id = list.add(task.title.clone(), 1);
+                let new = list.get_mut(id).unwrap();
+                new.source = Some(TaskSource { path: path.to_path_buf(), format, key: task.key.clone(), line: Some(task.line), done: task.done });
+                merge(new, task, true);
+                added += 1;
+            },
+        }
+    }
+    (added, updated, pending)
+}
+
+/// Keeps every file tasks were imported from in sync, checking them every few seconds.<br>
+/// Never returns, so it's meant to run in its own thread.
+pub fn watch(interval: Duration, mut on_change: impl FnMut(&Path, ImportSummary)) {
+    loop {
+        let tasks = unwrap_err!(TaskList::load(), else => TaskList::default());
+        let mut sources: Vec<(PathBuf, ImportFormat)> = Vec::new();
+        for source in tasks.tasks.iter().filter_map(|x| x.source.as_ref()) {
+            if !sources.iter().any(|(path, _)| *path == source.path) {
+                sources.push((source.path.clone(), source.format));
+            }
+        }
+
+        for (path, format) in sources {
+            let summary = unwrap_err!(import(&path, format), else => continue);
+            if summary.added + summary.updated + summary.written_back > 0 {
+                on_change(&path, summary);
+            }
+        }
+
+        thread::sleep(interval);
+    }
+}
+
+/// Starts watching the imported files in the background, logging what changes
+pub fn spawn_watcher() {
+    thread::spawn(|| watch(Duration::from_secs(5), |path, summary| {
+        log_info!(format!("Synced {}: {}", path.display(), summary));
+    }));
+}
+
+/// Whether a task changed after applying what was read from the file.<br>
+/// Completion is only taken when it changed in the file, so it doesn't undo the one done in overfocus.
+fn merge(task: &mut Task, external: ExternalTask, done_changed: bool) -> bool {
+    let before = (task.title.clone(), task.done, task.priority, task.project.clone(), task.tags.clone());
+
+    task.title = external.title;
+    if done_changed {
+        task.done = external.done;
+    }
+    task.priority = external.priority;
+    task.project = external.project;
+    task.tags = external.tags;
+
+    before != (task.title.clone(), task.done, task.priority, task.project.clone(), task.tags.clone())
+}
+
+fn parse(path: &Path, text: &str, format: ImportFormat) -> Result<Vec<ExternalTask>> {
+    let parse_line = match format {
+        ImportFormat::TodoTxt => parse_todo_txt,
+        ImportFormat::Markdown => parse_markdown,
+        ImportFormat::Taskwarrior => {
+            let Value::Array(items) = serde_json::from_str(text)? else { Err(ImportError::InvalidTaskwarrior(path.to_path_buf()))? };
+            return Ok(items.iter().enumerate().filter_map(|(i, x)| parse_taskwarrior(x).map(|task| ExternalTask { line: i, ..task })).collect());
+        },
+    };
+    Ok(parse_lines(text, parse_line))
+}
+
+/// Parses every line holding a task, giving each one its line and a key no other task of the file has
+fn parse_lines(text: &str, parse_line: fn(&str) -> Option<ExternalTask>) -> Vec<ExternalTask> {
+    let mut res: Vec<ExternalTask> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let Some(mut task) = parse_line(line) else { continue };
+        // Titles come from a single line, so the number can't be mistaken for part of one
+        let repeats = res.iter().filter(|x| x.key.split('\n').next() == Some(task.key.as_str())).count();
+        if repeats > 0 {
+            task.key = format!("{}\n{}", task.key, repeats + 1);
+        }
+        res.push(ExternalTask { line: i, ..task });
+    }
+    res
+}
+
+/// Marks the pending tasks as completed in the file read as `text`, returning how many were.<br>
+/// Their sources are left as not done, so the next import sees the completion and takes it.
+/// That's also why a file edited since it was read is left alone, the next import retries on the new text.
+fn write_back(path: &Path, text: &str, format: ImportFormat, pending: &[String]) -> Result<usize> {
+    if pending.is_empty() || fs::read_to_string(path)? != text {
+        return Ok(0);
+    }
+
+    let (res, completed) = match format {
+        ImportFormat::TodoTxt => complete_lines(text, pending, parse_todo_txt, complete_todo_txt),
+        ImportFormat::Markdown => complete_lines(text, pending, parse_markdown, |line| line.replacen("[ ]", "[x]", 1)),
+        ImportFormat::Taskwarrior => {
+            let mut items: Vec<Value> = serde_json::from_str(text)?;
+            let mut completed = 0;
+            for item in items.iter_mut().filter(|x| x["uuid"].as_str().map_or(false, |uuid| pending.iter().any(|x| x == uuid))) {
+                item["status"] = Value::from("completed");
+                item["end"] = Value::from(Utc::now().format("%Y%m%dT%H%M%SZ").to_string());
+                completed += 1;
+            }
+            (serde_json::to_string_pretty(&items)? + "\n", completed)
+        },
+    };
+    if completed == 0 {
+        return Ok(0);
+    }
+
+    // Written next to the file and renamed over it, so a crash never leaves it half written
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".overfocus.tmp");
+    fs::write(&tmp, res)?;
+    fs::rename(tmp, path)?;
+    Ok(completed)
+}
+
+/// Rewrites the lines of the pending tasks as completed, leaving the rest untouched.<br>
+/// Returns the new text and how many lines were completed.
+fn complete_lines(text: &str, pending: &[String], parse: fn(&str) -> Option<ExternalTask>, complete: impl Fn(&str) -> String) -> (String, usize) {
+    let lines: Vec<usize> = parse_lines(text, parse).into_iter().filter(|x| !x.done && pending.contains(&x.key)).map(|x| x.line).collect();
+    let res: Vec<String> = text.lines().enumerate().map(|(i, line)| if lines.contains(&i) { complete(line) } else { line.to_string() }).collect();
+
+    let mut res = res.join("\n");
+    if text.ends_with('\n') {
+        res.push('\n');
+    }
+    (res, lines.len())
+}
+
+
+
+// · · ·  Parsers  · · · //
+
+/// `x 2023-01-02 2023-01-01 (A) Call mom +family @phone due:2023-01-03`
+fn parse_todo_txt(line: &str) -> Option<ExternalTask> {
+    let mut words: Vec<&str> = line.split_whitespace().collect();
+    if words.is_empty() {
+        return None;
+    }
+
+    let done = words[0] == "x";
+    if done {
+        words.remove(0);
+    }
+
+    // Priority, completion and creation dates, in whatever order they were written
+    let mut priority = None;
+    while let Some(word) = words.first() {
+        if let Some(c) = todo_txt_priority(word) {
+            priority = Some(c);
+        } else if chrono::NaiveDate::parse_from_str(word, "%Y-%m-%d").is_err() {
+            break;
+        }
+        words.remove(0);
+    }
+
+    let mut project = None;
+    let mut tags = Vec::new();
+    let mut id = None;
+    let mut title = Vec::new();
+    for word in words {
+        if let Some(name) = word.strip_prefix('+').filter(|x| !x.is_empty()) {
+            project.get_or_insert(name.to_string());
+        } else if let Some(name) = word.strip_prefix('@').filter(|x| !x.is_empty()) {
+            tags.push(name.to_string());
+        } else if let Some((key, value)) = todo_txt_tag(word) {
+            match key {
+                "pri" => priority = value.chars().next(),
+                "id" => id = Some(value),
+                _ => {},
+            }
+        } else {
+            title.push(word);
+        }
+    }
+
+    let title = title.join(" ");
+    if title.is_empty() {
+        return None;
+    }
+    let key = id.map_or_else(|| title.clone(), |id| format!("id:{}", id));
+    Some(ExternalTask { key, line: 0, title, done, priority, project, tags })
+}
+
+/// `due:2023-01-03` gives its key and value, while `10:30` or `https://example.com` aren't tags
+fn todo_txt_tag(word: &str) -> Option<(&str, &str)> {
+    let (key, value) = word.split_once(':')?;
+    let valid_key = key.starts_with(|c: char| c.is_ascii_alphabetic()) && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    let valid_value = !value.is_empty() && !value.contains(':') && !value.starts_with("//");
+    (valid_key && valid_value).then_some((key, value))
+}
+
+/// `(A)` gives `A`
+fn todo_txt_priority(word: &str) -> Option<char> {
+    let chars: Vec<char> = word.chars().collect();
+    match chars.as_slice() {
+        ['(', c @ 'A'..='Z', ')'] => Some(*c),
+        _ => None,
+    }
+}
+
+/// Completed tasks lose their priority, which is kept as a `pri:` tag
+fn complete_todo_txt(line: &str) -> String {
+    let date = Local::now().format("%Y-%m-%d");
+    let line = line.trim_start();
+    match line.split_once(' ').filter(|(first, _)| todo_txt_priority(first).is_some()) {
+        Some((priority, rest)) => format!("x {} {} pri:{}", date, rest, &priority[1..2]),
+        None => format!("x {} {}", date, line),
+    }
+}
+
+/// `- [ ] Write the intro #writing`
+fn parse_markdown(line: &str) -> Option<ExternalTask> {
+    let rest = line.trim_start().strip_prefix(['-', '*', '+'])?.trim_start();
+    let (done, rest) = if let Some(rest) = rest.strip_prefix("[ ]") {
+        (false, rest)
+    } else {
+        (true, rest.strip_prefix("[x]").or_else(|| rest.strip_prefix("[X]"))?)
+    };
+
+    let mut tags = Vec::new();
+    let mut title = Vec::new();
+    for word in rest.split_whitespace() {
+        match word.strip_prefix('#').filter(|x| !x.is_empty()) {
+            Some(tag) => tags.push(tag.to_string()),
+            None => title.push(word),
+        }
+    }
+
+    let title = title.join(" ");
+    if title.is_empty() {
+        return None;
+    }
+    Some(ExternalTask { key: title.clone(), line: 0, title, done, priority: None, project: None, tags })
+}
+
+/// An object from `task export`, deleted tasks are skipped
+fn parse_taskwarrior(item: &Value) -> Option<ExternalTask> {
+    let status = item["status"].as_str()?;
+    if status == "deleted" {
+        return None;
+    }
+
+    // Taskwarrior priorities map to the first todo.txt ones
+    let priority = match item["priority"].as_str() {
+        Some("H") => Some('A'),
+        Some("M") => Some('B'),
+        Some("L") => Some('C'),
+        _ => None,
+    };
+
+    Some(ExternalTask {
+        key: item["uuid"].as_str()?.to_string(),
+        line: 0,
+        title: item["description"].as_str()?.to_string(),
+        done: status == "completed",
+        priority,
+        project: item["project"].as_str().map(String::from),
+        tags: item["tags"].as_array().map(|x| x.iter().filter_map(|x| x.as_str().map(String::from)).collect()).unwrap_or_default(),
+    })
+}
+
+impl ImportFormat {
+    /// Guesses the format from the file name
+    pub fn detect(path: &Path) -> Result<Self> {
+        let name = path.file_name().and_then(|x| x.to_str()).unwrap_or_default().to_lowercase();
+        match path.extension().and_then(|x| x.to_str()).map(|x| x.to_lowercase()).as_deref() {
+            Some("json") => Ok(ImportFormat::Taskwarrior),
+            Some("md" | "markdown") => Ok(ImportFormat::Markdown),
+            Some("txt") if name.contains("todo") || name.contains("done") => Ok(ImportFormat::TodoTxt),
+            _ => Err(ImportError::UndetectedFormat(path.to_path_buf()))?,
+        }
+    }
+}
+
+impl FromStr for ImportFormat {
+    type Err = ImportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "todotxt" | "todo.txt" => Ok(ImportFormat::TodoTxt),
+            "taskwarrior" | "tw" => Ok(ImportFormat::Taskwarrior),
+            "markdown" | "md" => Ok(ImportFormat::Markdown),
+            _ => Err(ImportError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} added, {} updated, {} completed in the file", self.added, self.updated, self.written_back)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::temp_dir;
+
+    fn todo(line: &str) -> ExternalTask {
+        parse_todo_txt(line).unwrap()
+    }
+
+    /// Parses the lines of a todo.txt file the way `parse` does
+    fn todo_file(text: &str) -> Vec<ExternalTask> {
+        parse_lines(text, parse_todo_txt)
+    }
+
+    #[test]
+    fn todo_txt_fields() {
+        let task = todo("x 2023-01-02 2023-01-01 (A) Call mom +family @phone @home due:2023-01-03");
+        assert!(task.done);
+        assert_eq!(task.title, "Call mom");
+        assert_eq!(task.priority, Some('A'));
+        assert_eq!(task.project.as_deref(), Some("family"));
+        assert_eq!(task.tags, ["phone", "home"]);
+        assert_eq!(task.key, "Call mom");
+    }
+
+    #[test]
+    fn todo_txt_keeps_words_that_arent_tags() {
+        assert_eq!(todo("Call at 10:30").title, "Call at 10:30");
+        assert_eq!(todo("Read https://example.com/a:b later").title, "Read https://example.com/a:b later");
+        assert_eq!(todo("Ratio 16:9: check").title, "Ratio 16:9: check");
+        assert_eq!(todo("Fix a:b:c").title, "Fix a:b:c");
+        assert_eq!(todo("Trailing key: here").title, "Trailing key: here");
+    }
+
+    #[test]
+    fn todo_txt_tags() {
+        let task = todo("x Ship it pri:B id:ship-1 rec:1w");
+        assert_eq!(task.title, "Ship it");
+        assert_eq!(task.priority, Some('B'));
+        assert_eq!(task.key, "id:ship-1");
+        assert!(parse_todo_txt("   ").is_none());
+        assert!(parse_todo_txt("(A) due:2023-01-01").is_none());
+    }
+
+    #[test]
+    fn todo_txt_completion() {
+        assert!(complete_todo_txt("(A) Call mom").ends_with(" Call mom pri:A"));
+        assert!(complete_todo_txt("Call mom").starts_with("x "));
+        let completed = complete_todo_txt("(B) Call at 10:30 +family");
+        assert_eq!(todo(&completed).title, "Call at 10:30");
+        assert_eq!(todo(&completed).priority, Some('B'));
+        assert!(todo(&completed).done);
+    }
+
+    #[test]
+    fn markdown() {
+        let task = parse_markdown("  - [ ] Write the intro #writing").unwrap();
+        assert!(!task.done);
+        assert_eq!(task.title, "Write the intro");
+        assert_eq!(task.tags, ["writing"]);
+        assert!(parse_markdown("* [X] Done").unwrap().done);
+        assert!(parse_markdown("- plain item").is_none());
+        assert!(parse_markdown("- [ ] #only-tags").is_none());
+    }
+
+    #[test]
+    fn taskwarrior() {
+        let item = serde_json::json!({"uuid": "abc", "description": "Fix it", "status": "completed", "priority": "M", "project": "home", "tags": ["a", "b"]});
+        let task = parse_taskwarrior(&item).unwrap();
+        assert_eq!(task.key, "abc");
+        assert!(task.done);
+        assert_eq!(task.priority, Some('B'));
+        assert_eq!(task.project.as_deref(), Some("home"));
+        assert_eq!(task.tags, ["a", "b"]);
+        assert!(parse_taskwarrior(&serde_json::json!({"uuid": "x", "description": "Gone", "status": "deleted"})).is_none());
+    }
+
+    #[test]
+    fn edited_title_keeps_the_task() {
+        let path = Path::new("/todo.txt");
+        let mut list = TaskList::default();
+        assert_eq!(apply(&mut list, path, ImportFormat::TodoTxt, todo_file("Call mom\nBuy milk")).0, 2);
+        let id = list.tasks[1].id;
+
+        let (added, updated, _) = apply(&mut list, path, ImportFormat::TodoTxt, todo_file("Call mom\nBuy oat milk"));
+        assert_eq!((added, updated), (0, 1));
+        assert_eq!(list.tasks.len(), 2);
+        assert_eq!(list.tasks[1].id, id);
+        assert_eq!(list.tasks[1].title, "Buy oat milk");
+        assert_eq!(list.tasks[1].source.as_ref().unwrap().key, "Buy oat milk");
+
+        // Tasks from before lines were kept aren't matched by line
+        list.tasks[1].source.as_mut().unwrap().line = None;
+        let (added, _, _) = apply(&mut list, path, ImportFormat::TodoTxt, todo_file("Call mom\nBuy almond milk"));
+        assert_eq!(added, 1);
+        list.tasks.pop();
+        list.tasks[1].source.as_mut().unwrap().line = Some(1);
+
+        // Moved lines are still found by their text
+        apply(&mut list, path, ImportFormat::TodoTxt, todo_file("New one\nCall mom\nBuy oat milk"));
+        assert_eq!(list.tasks.len(), 3);
+        assert_eq!(list.tasks[1].source.as_ref().unwrap().line, Some(2));
+    }
+
+    #[test]
+    fn completion_goes_both_ways() {
+        let path = Path::new("/todo.txt");
+        let mut list = TaskList::default();
+        apply(&mut list, path, ImportFormat::TodoTxt, todo_file("Call mom"));
+
+        // Completed in overfocus, so the file gets it
+        list.tasks[0].done = true;
+        let (_, _, pending) = apply(&mut list, path, ImportFormat::TodoTxt, todo_file("Call mom"));
+        assert_eq!(pending, ["Call mom"]);
+        assert!(list.tasks[0].done);
+
+        // Written back, then reopened in the file
+        apply(&mut list, path, ImportFormat::TodoTxt, todo_file("x Call mom"));
+        assert!(list.tasks[0].done);
+        let (_, updated, pending) = apply(&mut list, path, ImportFormat::TodoTxt, todo_file("Call mom"));
+        assert!(pending.is_empty());
+        assert_eq!(updated, 1);
+        assert!(!list.tasks[0].done);
+    }
+
+    #[test]
+    fn repeated_titles_stay_apart() {
+        let path = Path::new("/todo.txt");
+        let text = "Buy milk\nCall mom\nBuy milk +work";
+        let mut list = TaskList::default();
+        assert_eq!(apply(&mut list, path, ImportFormat::TodoTxt, todo_file(text)).0, 3);
+        assert_eq!(list.tasks[2].project.as_deref(), Some("work"));
+        assert_eq!(list.tasks[2].source.as_ref().unwrap().line, Some(2));
+        assert_eq!(list.tasks[0].source.as_ref().unwrap().line, Some(0));
+
+        // Only the one completed in overfocus is completed in the file
+        list.tasks[2].done = true;
+        let (added, _, pending) = apply(&mut list, path, ImportFormat::TodoTxt, todo_file(text));
+        assert_eq!((added, pending.len()), (0, 1));
+        let completed: Vec<bool> = todo_file(&complete_lines(text, &pending, parse_todo_txt, complete_todo_txt).0).iter().map(|x| x.done).collect();
+        assert_eq!(completed, [false, false, true]);
+    }
+
+    #[test]
+    fn write_back_leaves_edited_files_alone() {
+        let dir = temp_dir();
+        let path = dir.join("todo.txt");
+        let text = "Call mom\nBuy milk\n";
+        fs::write(&path, text).unwrap();
+
+        // Only tasks still in the file count
+        let pending = ["Call mom".to_string(), "Gone".to_string()];
+        assert_eq!(write_back(&path, text, ImportFormat::TodoTxt, &pending).unwrap(), 1);
+        let written = fs::read_to_string(&path).unwrap();
+        assert_eq!(todo_file(&written).iter().map(|x| x.done).collect::<Vec<_>>(), [true, false]);
+        assert_eq!(fs::read_dir(&*dir).unwrap().count(), 1);
+
+        // A file saved since it was read keeps the new edit
+        let edited = "Call mom\nBuy milk\nWalk the dog\n";
+        fs::write(&path, edited).unwrap();
+        assert_eq!(write_back(&path, text, ImportFormat::TodoTxt, &pending).unwrap(), 0);
+        assert_eq!(fs::read_to_string(&path).unwrap(), edited);
+    }
+}
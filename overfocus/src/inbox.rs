@@ -0,0 +1,62 @@
+use std::sync::Mutex;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::{storage, tasks::TaskList};
+
+const INBOX_FILE: &str = "inbox.json";
+
+/// Serializes every read-modify-write of the inbox file
+static INBOX_LOCK: Mutex<()> = Mutex::new(());
+
+/// A stray thought captured mid-pomodoro, to be reviewed later
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InboxEntry {
+    pub id: Uuid,
+    pub text: String,
+    pub at: DateTime<Utc>,
+}
+
+/// Every captured thought, persisted in the data directory
+#[derive(Default, Serialize, Deserialize)]
+pub struct Inbox {
+    pub entries: Vec<InboxEntry>,
+}
+
+impl Inbox {
+    pub fn load() -> Result<Self> {
+        let _lock = INBOX_LOCK.lock().unwrap();
+        storage::load(INBOX_FILE)
+    }
+
+    /// Loads the inbox, modifies it and saves it back in one go
+    pub fn update<T>(func: impl FnOnce(&mut Inbox) -> T) -> Result<T> {
+        let _lock = INBOX_LOCK.lock().unwrap();
+        let mut inbox: Inbox = storage::load(INBOX_FILE)?;
+        let res = func(&mut inbox);
+        storage::save(INBOX_FILE, &inbox)?;
+        Ok(res)
+    }
+
+    /// Appends a thought to the inbox
+    pub fn capture(text: String) -> Result<()> {
+        Self::update(|inbox| inbox.entries.push(InboxEntry { id: Uuid::new_v4(), text, at: Utc::now() }))
+    }
+
+    pub fn remove(id: Uuid) -> Result<()> {
+        Self::update(|inbox| inbox.entries.retain(|x| x.id != id))
+    }
+
+    /// Moves an entry out of the inbox and into the task list.<br>
+    /// The entry is only removed once the task is saved, so a failure never loses it.
+    pub fn into_task(id: Uuid) -> Result<Option<Uuid>> {
+        let Some(entry) = Self::load()?.entries.into_iter().find(|x| x.id == id) else { return Ok(None) };
+
+        let task = TaskList::update(|list| list.add(entry.text, 1))?;
+        Self::remove(id)?;
+        Ok(Some(task))
+    }
+}
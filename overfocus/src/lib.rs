@@ -2,4 +2,24 @@
 #![allow(clippy::all)]
 
 pub mod pomodoro;
-pub mod logger;
\ No newline at end of file
+pub mod logger;
+pub mod storage;
+pub mod config;
+pub mod history;
+pub mod events;
+pub mod retention;
+pub mod edits;
+pub mod sync;
+pub mod days;
+pub mod stats;
+pub mod goals;
+pub mod achievements;
+pub mod tasks;
+pub mod inbox;
+pub mod tags;
+pub mod export;
+pub mod import;
+pub mod report;
+
+#[cfg(test)]
+mod testing;
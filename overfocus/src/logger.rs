@@ -1,4 +1,4 @@
-use std::{sync::{Mutex, OnceLock}, time::Instant};
+use std::{sync::{Mutex, OnceLock, TryLockError}, time::Instant, collections::VecDeque};
 
 // · · ·  Macro Definitions  · · · //
 
@@ -74,7 +74,8 @@ pub enum Duration { Short, Long }
 
 pub struct Logger {
     logs: Vec<LogData>,
-    notification: Option<NotificationData>,
+    /// Notifications waiting to be shown, oldest first
+    notifications: VecDeque<NotificationData>,
     start: Instant,
 }
 
@@ -83,7 +84,7 @@ static LOGGER: OnceLock<Mutex<Logger>> = OnceLock::new();
 
 impl Logger {
     fn new() -> Mutex<Self> {
-        Mutex::new(Self { logs: Vec::new(), start: Instant::now(), notification: None })
+        Mutex::new(Self { logs: Vec::new(), start: Instant::now(), notifications: VecDeque::new() })
     }
     
     pub fn init() {
@@ -100,7 +101,7 @@ impl Logger {
     pub fn notify(text: String, duration: Duration) {
         { // Scope here so there's no lock
             let mut logger = LOGGER.get_or_init(Self::new).lock().unwrap();
-            logger.notification = Some(NotificationData(text.clone(), duration))
+            logger.notifications.push_back(NotificationData(text.clone(), duration))
         }
 
         Logger::log(text, LogKind::Info);
@@ -110,11 +111,18 @@ impl Logger {
         LOGGER.get_or_init(Self::new).lock().unwrap().logs.last().map(|x| x.clone())
     }
 
-    pub fn consume_notification() -> Option<NotificationData> {
-        let mut logger = LOGGER.get_or_init(Self::new).lock().unwrap();
+    /// The last `count` logs, oldest first.<br>
+    /// Gives nothing rather than wait on the lock, so it's safe to call when crashing.
+    pub fn recent(count: usize) -> Vec<LogData> {
+        let logger = match LOGGER.get_or_init(Self::new).try_lock() {
+            Ok(x) => x,
+            Err(TryLockError::Poisoned(x)) => x.into_inner(),
+            Err(TryLockError::WouldBlock) => return Vec::new(),
+        };
+        logger.logs[logger.logs.len().saturating_sub(count)..].to_vec()
+    }
 
-        let tmp = logger.notification.clone();
-        logger.notification = None;
-        return tmp;
+    pub fn consume_notification() -> Option<NotificationData> {
+        LOGGER.get_or_init(Self::new).lock().unwrap().notifications.pop_front()
     }
 }
\ No newline at end of file
@@ -1,15 +1,40 @@
-use std::{sync::{Mutex, Arc, MutexGuard}, thread, time::Duration};
+use std::{sync::{Mutex, Arc, MutexGuard, TryLockError}, thread, time::Duration, fs};
 
 use anyhow::{Result, Ok};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
 use thiserror::Error;
+use uuid::Uuid;
 
-use crate::{unwrap_err, log_info, log_warn, log_err, notify_short, notify_long};
+use crate::{storage, unwrap_err, log_info, log_warn, log_err, notify_short, notify_long, config::{Config, StrictMode}, goals, achievements::{Achievements, Progress}, days, tasks::TaskList, tags::merge_tags, history::{History, Session, Outcome, AbandonReason, Interruption, InterruptionKind}, events::Event};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum PomodoroStage {
     Work, ShortBreak, LongBreak
 }
 
+impl PomodoroStage {
+    /// How long the stage lasts, in seconds
+    pub fn length_secs(&self) -> usize {
+        match self {
+            PomodoroStage::Work => 25 * 60,
+            PomodoroStage::ShortBreak => 5 * 60,
+            PomodoroStage::LongBreak => 30 * 60,
+        }
+    }
+}
+
+/// Work stages of a set, the last one being followed by the long break
+pub const SET_LENGTH: u8 = 3;
+
+const TIMER_FILE: &str = "timer.json";
+
+/// The last state of the running clock, kept for when the app crashes
+static LAST_SNAPSHOT: Mutex<Option<PomodoroSnapshot>> = Mutex::new(None);
+
+/// Taken before letting go of a clock with chores to do, so they're done in the order they were left
+static CHORES_LOCK: Mutex<()> = Mutex::new(());
+
 #[derive(Clone, Copy)]
 enum UserInputFlags {
     None,
@@ -17,6 +42,15 @@ enum UserInputFlags {
     Stop,
 }
 
+/// Work a change to the clock leaves to do once its lock is released, as it goes to disk
+enum Chore {
+    Log(Event),
+    /// Credits the completed work stage to a task
+    Credit(Uuid),
+    CountGoal,
+    UnlockMilestones { clean_set: bool },
+}
+
 /// Main struct that allows the execution of a Pomodoro clock.<br>
 /// A pomodoro clock consists of the following:<br>
 /// - 3 cycles of 25 minutes of work and 5 minute breaks
@@ -24,10 +58,41 @@ enum UserInputFlags {
 pub struct Pomodoro {
     stage: PomodoroStage,
     repetitions: u8,
+    /// Work stages completed since the clock started, voided ones left out
     pomodoros: u8,
     seconds: usize,
 
+    strict: StrictMode,
+    stage_start: DateTime<Utc>,
+    /// Id the current stage will be recorded with
+    session: Uuid,
+    paused_secs: u64,
+    voided: Option<AbandonReason>,
+    interruptions: Vec<Interruption>,
+    task: Option<Uuid>,
+    project: Option<String>,
+    tags: Vec<String>,
+    finished_work: Option<Uuid>,
+    /// Whether the current set went without interruptions or voided stages so far
+    clean_set: bool,
+    /// Left by the last change, done once the clock is unlocked
+    chores: Vec<Chore>,
+
     input_flags: UserInputFlags,
+    /// Called whenever what the clock shows changes
+    on_change: Option<Box<dyn Fn() + Send>>,
+}
+
+/// Settings a pomodoro clock is started with
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct PomodoroOptions {
+    pub strict: StrictMode,
+    /// Task that gets credited with every completed work stage
+    pub task: Option<Uuid>,
+    /// Project the sessions are billed to, the task's one if none is given
+    pub project: Option<String>,
+    /// Tags added to the sessions, along with the task's ones
+    pub tags: Vec<String>,
 }
 
 
@@ -39,33 +104,148 @@ pub enum PomodoroError {
 
 pub type PomodoroHandle = Arc<Mutex<Pomodoro>>;
 
+/// What a clock needs to carry on where it was, saved to the data directory when the app crashes
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PomodoroSnapshot {
+    pub stage: PomodoroStage,
+    pub repetitions: u8,
+    pub pomodoros: u8,
+    pub seconds: usize,
+    pub strict: StrictMode,
+    pub stage_start: DateTime<Utc>,
+    pub session: Uuid,
+    pub paused: bool,
+    pub voided: Option<AbandonReason>,
+    pub interruptions: Vec<Interruption>,
+    pub task: Option<Uuid>,
+    pub project: Option<String>,
+    pub tags: Vec<String>,
+    pub clean_set: bool,
+    pub saved_at: DateTime<Utc>,
+}
+
 impl Pomodoro {
     // · · ·  Main Thread Functions  · · · //
     
     /// Starts a new pomodoro clock with its own thread
-    pub fn start() -> PomodoroHandle {
-        let pomodoro = Arc::new(Mutex::new(Self {
+    pub fn start(mut options: PomodoroOptions) -> PomodoroHandle {
+        // Sessions inherit the project and tags of the task they're for
+        let task = options.task.and_then(|id| unwrap_err!(TaskList::load(), else => TaskList::default()).get(id).cloned());
+        if let Some(task) = task {
+            options.project = options.project.or(task.project);
+            merge_tags(&mut options.tags, &task.tags);
+        }
+
+        let pomodoro = Self::new(options);
+        notify_short!("Pomodoro clock started.");
+        Self::spawn(pomodoro)
+    }
+
+    /// A clock at the start of its first work stage, which is logged once it runs
+    fn new(options: PomodoroOptions) -> Self {
+        let mut pomodoro = Self {
             stage: PomodoroStage::Work,
             repetitions: 0,
-            pomodoros: 2,
+            pomodoros: 0,
             seconds: 0,
+            strict: options.strict,
+            stage_start: Utc::now(),
+            session: Uuid::new_v4(),
+            paused_secs: 0,
+            voided: None,
+            interruptions: Vec::new(),
+            task: options.task,
+            project: options.project,
+            tags: options.tags,
+            finished_work: None,
+            clean_set: true,
+            chores: Vec::new(),
             input_flags: UserInputFlags::None,
-        }));
+            on_change: None,
+        };
+        pomodoro.log_stage_start();
+        pomodoro
+    }
+
+    /// Carries on with a clock saved by a crash, in the same stage and with the time it had
+    pub fn restore(snapshot: PomodoroSnapshot) -> PomodoroHandle {
+        let pomodoro = Self {
+            stage: snapshot.stage,
+            repetitions: snapshot.repetitions,
+            pomodoros: snapshot.pomodoros,
+            seconds: snapshot.seconds,
+            strict: snapshot.strict,
+            stage_start: snapshot.stage_start,
+            session: snapshot.session,
+            paused_secs: 0,
+            voided: snapshot.voided,
+            interruptions: snapshot.interruptions,
+            task: snapshot.task,
+            project: snapshot.project,
+            tags: snapshot.tags,
+            finished_work: None,
+            clean_set: snapshot.clean_set,
+            chores: Vec::new(),
+            input_flags: if snapshot.paused { UserInputFlags::Pause } else { UserInputFlags::None },
+            on_change: None,
+        };
+
+        // The time the app was down doesn't count as focus
+        unwrap_err!(History::log(Event::Paused { at: snapshot.saved_at }));
+        if !snapshot.paused {
+            unwrap_err!(History::log(Event::Resumed { at: Utc::now() }));
+        }
+        notify_short!("Pomodoro clock restored from before the crash.");
+
+        Self::spawn(pomodoro)
+    }
+
+    /// Runs a clock on its own thread
+    fn spawn(mut pomodoro: Self) -> PomodoroHandle {
+        for chore in std::mem::take(&mut pomodoro.chores) {
+            chore.run();
+        }
+        pomodoro.remember();
+        let pomodoro = Arc::new(Mutex::new(pomodoro));
 
         let thread_pomodoro = pomodoro.clone();
         thread::spawn(|| unwrap_err!(Self::tick(thread_pomodoro)));
 
-        notify_short!("Pomodoro clock started.");
-
         pomodoro
     }
 
+    /// Saves the last state of the running clock, so the next start can restore it.<br>
+    /// Meant for crashes, so it gives up rather than wait on a lock.
+    pub fn save_snapshot() -> Result<Option<PomodoroSnapshot>> {
+        let snapshot = match LAST_SNAPSHOT.try_lock() {
+            std::result::Result::Ok(x) => x.clone(),
+            Err(TryLockError::Poisoned(x)) => x.into_inner().clone(),
+            Err(TryLockError::WouldBlock) => None,
+        };
+        if let Some(snapshot) = &snapshot {
+            storage::save(TIMER_FILE, snapshot)?;
+        }
+        Ok(snapshot)
+    }
+
+    /// Takes the clock saved by a crash, if there's one
+    pub fn take_snapshot() -> Result<Option<PomodoroSnapshot>> {
+        let path = storage::data_dir()?.join(TIMER_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let snapshot = storage::load_from(&path)?;
+        fs::remove_file(path)?;
+        Ok(snapshot)
+    }
+
     /// Pauses the pomodoro progression, but the thread remains
     pub fn pause(data: &PomodoroHandle) -> Result<()> {
         log_warn!("Pomodoro clock paused.");
 
-        Self::lock_and(data, |mut x| {
+        Self::update(data, |x| {
             x.input_flags = UserInputFlags::Pause;
+            x.chores.push(Chore::Log(Event::Paused { at: Utc::now() }));
         })
     }
     
@@ -73,26 +253,79 @@ impl Pomodoro {
     pub fn resume(data: &PomodoroHandle) -> Result<()> {
         log_err!("Pomodoro clock resumed.");
         
-        Self::lock_and(data, |mut x| {
+        Self::update(data, |x| {
             x.input_flags = UserInputFlags::None;
+            x.paused_secs = 0;
+
+            // A voided pomodoro starts over when resumed
+            if x.voided.take().is_some() {
+                x.stage_start = Utc::now();
+                x.log_stage_start();
+            } else {
+                x.chores.push(Chore::Log(Event::Resumed { at: Utc::now() }));
+            }
         })
     }
 
-    /// Halts the pomodoro thread
+    /// Halts the pomodoro thread, recording the current stage as abandoned
     pub fn stop(data: &PomodoroHandle) -> Result<()> {
         log_info!("Pomodoro clock stopped.");
         
-        Self::lock_and(data, |mut x| {
+        Self::update(data, |x| {
             x.input_flags = UserInputFlags::Stop;
+            *LAST_SNAPSHOT.lock().unwrap_or_else(|x| x.into_inner()) = None;
+            if x.voided.is_none() {
+                x.finish_stage(Outcome::Abandoned(AbandonReason::Stopped));
+            }
+        })
+    }
+
+    /// Cuts the current stage short and moves on to the next one, recording it as skipped
+    pub fn skip(data: &PomodoroHandle) -> Result<()> {
+        Self::update(data, |x| x.handle_skip())
+    }
+
+    /// Logs an interruption against the current pomodoro without pausing it
+    pub fn interrupt(data: &PomodoroHandle, kind: InterruptionKind, note: Option<String>) -> Result<()> {
+        Self::update(data, |x| {
+            if x.stage != PomodoroStage::Work {
+                log_warn!("Interruptions can only be logged during work.");
+                return;
+            }
+
+            log_info!(format!("Logged {} interruption.", kind));
+            x.clean_set = false;
+            let interruption = Interruption { kind, at: Utc::now(), note };
+            x.chores.push(Chore::Log(Event::Interrupted { interruption: interruption.clone() }));
+            x.interruptions.push(interruption);
         })
     }
 
+    /// Calls `callback` from the clock's thread every time what it shows changes, instead of the last one given
+    pub fn on_change(data: &PomodoroHandle, callback: impl Fn() + Send + 'static) -> Result<()> {
+        Self::lock_and(data, |mut x| x.on_change = Some(Box::new(callback)))
+    }
+
     /// Locks a handle and allows to do something with it
     pub fn lock_and<T>(data: &PomodoroHandle, func: impl FnOnce(MutexGuard<Pomodoro>) -> T) -> Result<T> {
         let locked = data.lock().map_err(|_| PomodoroError::PoisonedThread)?;
         Ok(func(locked))
     }
 
+    /// Locks a handle to change the clock, doing the chores the change left once the lock is released
+    fn update<T>(data: &PomodoroHandle, func: impl FnOnce(&mut Pomodoro) -> T) -> Result<T> {
+        let mut locked = data.lock().map_err(|_| PomodoroError::PoisonedThread)?;
+        let res = func(&mut locked);
+        let chores = std::mem::take(&mut locked.chores);
+        let _chores_lock = CHORES_LOCK.lock().unwrap_or_else(|x| x.into_inner());
+        drop(locked);
+
+        for chore in chores {
+            chore.run();
+        }
+        Ok(res)
+    }
+
     pub fn stage(&self) -> &PomodoroStage {
         &self.stage
     }
@@ -109,6 +342,59 @@ impl Pomodoro {
         self.seconds
     }
 
+    /// Seconds left until the current stage ends
+    pub fn remaining_secs(&self) -> usize {
+        self.stage.length_secs().saturating_sub(self.seconds)
+    }
+
+    /// The interruptions logged during the current stage
+    pub fn interruptions(&self) -> &[Interruption] {
+        &self.interruptions
+    }
+
+    /// Takes the id of the last work session that finished, so it's only handled once
+    pub fn take_finished_work(&mut self) -> Option<Uuid> {
+        self.finished_work.take()
+    }
+
+    /// The reason the current pomodoro was voided, if it was
+    pub fn voided(&self) -> Option<AbandonReason> {
+        self.voided
+    }
+
+    pub fn paused(&self) -> bool {
+        matches!(self.input_flags, UserInputFlags::Pause)
+    }
+
+    pub fn snapshot(&self) -> PomodoroSnapshot {
+        PomodoroSnapshot {
+            stage: self.stage,
+            repetitions: self.repetitions,
+            pomodoros: self.pomodoros,
+            seconds: self.seconds,
+            strict: self.strict,
+            stage_start: self.stage_start,
+            session: self.session,
+            paused: self.paused(),
+            voided: self.voided,
+            interruptions: self.interruptions.clone(),
+            task: self.task,
+            project: self.project.clone(),
+            tags: self.tags.clone(),
+            clean_set: self.clean_set,
+            saved_at: Utc::now(),
+        }
+    }
+
+    /// Keeps the state in memory for a crash to save, which is cheaper than writing it every second
+    fn remember(&self) {
+        // A stopped clock has nothing left to restore
+        if matches!(self.input_flags, UserInputFlags::Stop) {
+            return;
+        }
+        *LAST_SNAPSHOT.lock().unwrap_or_else(|x| x.into_inner()) = Some(self.snapshot());
+    }
+
 
 
     // · · ·  Pomodoro Thread Functions  · · · //
@@ -121,66 +407,323 @@ impl Pomodoro {
             
             // Guard clause for user input
             match Self::lock_and(&data, |x| x.input_flags)? {
-                UserInputFlags::Pause => continue,
+                UserInputFlags::Pause => {
+                    Self::update(&data, |data| {
+                        data.handle_pause();
+                        data.remember();
+                    })?;
+                    continue
+                },
                 UserInputFlags::Stop => return Ok(()),
                 _ => {}
             }
             
             // Actually do things
-            Self::lock_and(&data, |mut data| {
+            Self::update(&data, |data| {
                 match data.stage {
-                    PomodoroStage::Work => Self::handle_work(&mut data),
-                    PomodoroStage::ShortBreak => Self::handle_short_break(&mut data),
-                    PomodoroStage::LongBreak => Self::handle_long_break(&mut data),
+                    PomodoroStage::Work => data.handle_work(),
+                    PomodoroStage::ShortBreak => data.handle_short_break(),
+                    PomodoroStage::LongBreak => data.handle_long_break(),
                 }
 
                 data.seconds += 1;
+                data.remember();
+                data.changed();
             })?;
         }
     }
 
-    fn handle_work(data: &mut MutexGuard<Pomodoro>) {
-        if data.seconds < 25 * 60 {
+    fn handle_work(&mut self) {
+        if self.seconds < self.stage.length_secs() {
             return;
         }
         
-        if data.repetitions == 2 {
-            data.start_long_break()
-        } else {
-            data.start_short_break()
+        self.finish_stage(Outcome::Completed);
+        self.pomodoros += 1;
+        self.credit_task();
+        let clean_set = self.repetitions == SET_LENGTH - 1 && self.clean_set;
+        self.next_stage();
+        self.chores.push(Chore::CountGoal);
+        self.chores.push(Chore::UnlockMilestones { clean_set });
+    }
+
+    fn handle_short_break(&mut self) {
+        if self.seconds >= self.stage.length_secs() {
+            self.finish_stage(Outcome::Completed);
+            self.next_stage();
         }
     }
 
-    fn handle_short_break(data: &mut MutexGuard<Pomodoro>) {
-        if data.seconds >= 5 * 60 {
-            data.start_work();
-            data.repetitions += 1;
+    fn handle_long_break(&mut self) {
+        if self.seconds >= self.stage.length_secs() {
+            self.finish_stage(Outcome::Completed);
+            self.next_stage();
         }
     }
 
-    fn handle_long_break(data: &mut MutexGuard<Pomodoro>) {
-        if data.seconds >= 30 * 60 {
-            data.start_work();
-            data.pomodoros += 1;
-            data.repetitions = 0;
+    /// Records the current stage as skipped and starts the next one, a skipped work stage leaving the set unclean
+    fn handle_skip(&mut self) {
+        if self.voided.is_some() {
+            log_warn!("A voided pomodoro can only be restarted.");
+            return;
         }
+
+        log_info!("Stage skipped.");
+        self.finish_stage(Outcome::Abandoned(AbandonReason::Skipped));
+        if self.stage == PomodoroStage::Work {
+            self.clean_set = false;
+        }
+        self.next_stage();
+        self.remember();
+        self.changed();
     }
 
-    fn start_work(&mut self) {
+    /// Starts the stage that follows the current one in the set
+    fn next_stage(&mut self) {
+        match self.stage {
+            PomodoroStage::Work if self.repetitions >= SET_LENGTH - 1 => self.start_long_break(),
+            PomodoroStage::Work => self.start_short_break(),
+            PomodoroStage::ShortBreak => {
+                self.start_work();
+                self.repetitions += 1;
+            },
+            PomodoroStage::LongBreak => {
+                self.start_work();
+                self.repetitions = 0;
+                self.clean_set = true;
+            },
+        }
+    }
+
+    /// Counts the time paused and voids the pomodoro if strict mode says so
+    fn handle_pause(&mut self) {
+        if !self.strict.enabled || self.voided.is_some() || self.stage != PomodoroStage::Work {
+            return;
+        }
+
+        self.paused_secs += 1;
+        if self.paused_secs > self.strict.grace_secs {
+            let reason = if self.strict.grace_secs == 0 { AbandonReason::Paused } else { AbandonReason::PausedTooLong };
+            self.finish_stage(Outcome::Abandoned(reason));
+            self.voided = Some(reason);
+            self.clean_set = false;
+            self.seconds = 0;
+            log_warn!(format!("Pomodoro voided: {}.", reason));
+            self.changed();
+        }
+    }
+
+    fn changed(&self) {
+        if let Some(callback) = &self.on_change {
+            callback();
+        }
+    }
+
+    /// Records the current stage in the history
+    fn finish_stage(&mut self, outcome: Outcome) {
+        let id = self.session;
+        if self.stage == PomodoroStage::Work && outcome == Outcome::Completed {
+            self.finished_work = Some(id);
+        }
+
+        self.chores.push(Chore::Log(Event::StageFinished { session: Session {
+            id,
+            stage: self.stage,
+            start: self.stage_start,
+            end: Utc::now(),
+            focus_secs: self.seconds as u64,
+            outcome,
+            interruptions: std::mem::take(&mut self.interruptions),
+            task: self.task,
+            reflection: None,
+            project: self.project.clone(),
+            tags: self.tags.clone(),
+        }}));
+    }
+
+    /// Credits the completed work stage to the active task
+    fn credit_task(&mut self) {
+        let Some(id) = self.task else { return };
+        self.chores.push(Chore::Log(Event::TaskCredited { task: id, session: self.session }));
+        self.chores.push(Chore::Credit(id));
+    }
+
+    /// Notifies when the completed work stage is the one reaching the daily goal of the active profile
+    fn count_goal() {
+        let goals = unwrap_err!(Config::load(), else => Config::default()).goals();
+        let Some(history) = unwrap_err!(History::load().map(Some), else => None) else { return };
+        let progress = goals::progress(&history, &goals, days::today());
+        if progress.reached() && progress.done == progress.goal as usize {
+            notify_long!(format!("Daily goal of {} pomodoros reached!", progress.goal));
+        }
+    }
+
+    /// Notifies of every milestone the completed work stage reached
+    fn unlock_milestones(clean_set: bool) {
+        let Some(mut progress) = unwrap_err!(Progress::load(days::today()).map(Some), else => None) else { return };
+        progress.clean_set = clean_set;
+        for milestone in unwrap_err!(Achievements::unlock(&progress), else => Vec::new()) {
+            notify_long!(format!("Achievement unlocked: {}!", milestone.name()));
+        }
+    }
+
+    /// Gives the stage that just started its id and logs it
+    fn log_stage_start(&mut self) {
+        self.session = Uuid::new_v4();
+        self.chores.push(Chore::Log(Event::StageStarted {
+            id: self.session,
+            stage: self.stage,
+            at: self.stage_start,
+            task: self.task,
+            project: self.project.clone(),
+            tags: self.tags.clone(),
+        }));
+    }
+
+    /// Starts a stage from scratch, the grace period of strict mode included
+    fn start_stage(&mut self, stage: PomodoroStage) {
         self.seconds = 0;
-        self.stage = PomodoroStage::Work;
+        self.paused_secs = 0;
+        self.stage_start = Utc::now();
+        self.stage = stage;
+        self.log_stage_start();
+    }
+
+    fn start_work(&mut self) {
+        self.start_stage(PomodoroStage::Work);
         notify_long!("Work started!");
     }
 
     fn start_short_break(&mut self) {
-        self.seconds = 0;
-        self.stage = PomodoroStage::ShortBreak;
+        self.start_stage(PomodoroStage::ShortBreak);
         notify_long!("Break started (5 min)");
     }
 
     fn start_long_break(&mut self) {
-        self.seconds = 0;
-        self.stage = PomodoroStage::LongBreak;
+        self.start_stage(PomodoroStage::LongBreak);
         notify_long!("Break started (30 min)");
     }
-}
\ No newline at end of file
+}
+
+impl Chore {
+    fn run(self) {
+        match self {
+            Chore::Log(event) => unwrap_err!(History::log(event)),
+            Chore::Credit(task) => {
+                if let Some(title) = unwrap_err!(TaskList::credit(task), else => None) {
+                    log_info!(format!("Pomodoro credited to \"{}\".", title));
+                }
+            },
+            Chore::CountGoal => Pomodoro::count_goal(),
+            Chore::UnlockMilestones { clean_set } => Pomodoro::unlock_milestones(clean_set),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clock(enabled: bool, grace_secs: u64) -> Pomodoro {
+        Pomodoro::new(PomodoroOptions { strict: StrictMode { enabled, grace_secs }, ..Default::default() })
+    }
+
+    /// Outcomes of the stages the clock recorded so far
+    fn recorded(clock: &Pomodoro) -> Vec<Outcome> {
+        clock.chores.iter().filter_map(|x| match x {
+            Chore::Log(Event::StageFinished { session }) => Some(session.outcome.clone()),
+            _ => None,
+        }).collect()
+    }
+
+    fn pause_for(clock: &mut Pomodoro, secs: u64) {
+        clock.input_flags = UserInputFlags::Pause;
+        for _ in 0..secs {
+            clock.handle_pause();
+        }
+    }
+
+    #[test]
+    fn voids_work_paused_past_the_grace_period() {
+        let mut clock = clock(true, 3);
+        clock.seconds = 600;
+        pause_for(&mut clock, 3);
+        assert!(clock.voided.is_none());
+
+        pause_for(&mut clock, 1);
+        assert_eq!(clock.voided, Some(AbandonReason::PausedTooLong));
+        assert_eq!(recorded(&clock), [Outcome::Abandoned(AbandonReason::PausedTooLong)]);
+        assert_eq!((clock.seconds, clock.clean_set), (0, false));
+
+        // Only recorded once, however long it stays paused
+        pause_for(&mut clock, 10);
+        assert_eq!(recorded(&clock).len(), 1);
+    }
+
+    #[test]
+    fn voids_any_pause_without_grace() {
+        let mut clock = clock(true, 0);
+        pause_for(&mut clock, 1);
+        assert_eq!(clock.voided, Some(AbandonReason::Paused));
+    }
+
+    #[test]
+    fn only_voids_work_in_strict_mode() {
+        let mut relaxed = clock(false, 0);
+        pause_for(&mut relaxed, 60);
+        assert!(relaxed.voided.is_none());
+
+        let mut on_break = clock(true, 0);
+        on_break.next_stage();
+        pause_for(&mut on_break, 60);
+        assert!(on_break.voided.is_none());
+    }
+
+    #[test]
+    fn every_stage_gets_the_whole_grace_period() {
+        let mut clock = clock(true, 5);
+        pause_for(&mut clock, 4);
+        // Skipped while paused, through the break and back to work
+        clock.next_stage();
+        clock.next_stage();
+        assert_eq!(clock.stage, PomodoroStage::Work);
+        pause_for(&mut clock, 5);
+        assert!(clock.voided.is_none());
+    }
+
+    #[test]
+    fn skipped_work_is_recorded_without_credit() {
+        let mut clock = Pomodoro::new(PomodoroOptions { task: Some(Uuid::new_v4()), ..Default::default() });
+        clock.seconds = 600;
+        clock.handle_skip();
+
+        assert_eq!(recorded(&clock), [Outcome::Abandoned(AbandonReason::Skipped)]);
+        assert!(!clock.chores.iter().any(|x| matches!(x, Chore::Credit(_) | Chore::Log(Event::TaskCredited { .. }) | Chore::CountGoal)));
+        assert!(clock.finished_work.is_none());
+        assert_eq!((clock.stage, clock.seconds, clock.pomodoros, clock.clean_set), (PomodoroStage::ShortBreak, 0, 0, false));
+    }
+
+    #[test]
+    fn skipped_breaks_move_on_to_work() {
+        let mut clock = clock(false, 0);
+        clock.next_stage();
+        clock.handle_skip();
+
+        assert_eq!(recorded(&clock), [Outcome::Abandoned(AbandonReason::Skipped)]);
+        assert_eq!((clock.stage, clock.repetitions, clock.clean_set), (PomodoroStage::Work, 1, true));
+    }
+
+    #[test]
+    fn long_break_follows_the_last_work_of_a_set() {
+        let mut clock = clock(false, 0);
+        for _ in 0..SET_LENGTH - 1 {
+            clock.next_stage();
+            assert_eq!(clock.stage, PomodoroStage::ShortBreak);
+            clock.next_stage();
+        }
+        clock.next_stage();
+        assert_eq!(clock.stage, PomodoroStage::LongBreak);
+
+        clock.next_stage();
+        assert_eq!((clock.stage, clock.repetitions), (PomodoroStage::Work, 0));
+    }
+}
@@ -0,0 +1,359 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use chrono::{NaiveDate, Datelike, Duration};
+use thiserror::Error;
+
+use crate::{history::History, tasks::TaskList, stats::{self, DateRange, Totals}, config::Config, goals, days};
+
+/// How many tasks and projects make it to the top lists
+const TOP: usize = 5;
+
+#[derive(Error, Debug)]
+pub enum ReportError {
+    #[error("Unknown report format `{0}`")]
+    UnknownFormat(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ReportPeriod {
+    Day,
+    /// Monday to Sunday
+    Week,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    /// A standalone page, styles included
+    Html,
+    Text,
+}
+
+/// Summary of the work done in a day or week, next to the one before it
+pub struct Report {
+    pub period: ReportPeriod,
+    pub range: DateRange,
+    pub totals: Totals,
+    pub previous: Totals,
+    pub interruptions: usize,
+    pub previous_interruptions: usize,
+    pub streak: usize,
+    pub days: Vec<(NaiveDate, Totals)>,
+    pub tasks: Vec<(String, Totals)>,
+    pub projects: Vec<(String, Totals)>,
+}
+
+/// A titled table, which every format knows how to draw
+struct Section {
+    title: &'static str,
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Report {
+    /// Builds the report of the day or week containing `day`
+    pub fn new(history: &History, tasks: &TaskList, config: &Config, period: ReportPeriod, day: NaiveDate) -> Self {
+        let range = match period {
+            ReportPeriod::Day => DateRange { from: day, to: day },
+            ReportPeriod::Week => {
+                let from = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+                DateRange { from, to: from + Duration::days(6) }
+            },
+        };
+
+        let tasks = stats::by_task(history, range).into_iter().map(|(id, totals)| {
+            let title = match id {
+                Some(id) => tasks.get(id).map_or("(deleted task)", |x| x.title.as_str()),
+                None => "(no task)",
+            };
+            (title.to_string(), totals)
+        }).collect();
+
+        Self {
+            period,
+            range,
+            totals: stats::totals(history, range),
+            previous: stats::totals(history, range.previous()),
+            interruptions: stats::interruption_count(history, range),
+            previous_interruptions: stats::interruption_count(history, range.previous()),
+            streak: goals::streak(history, &config.goals(), config, range.to.min(days::today())),
+            days: stats::per_day(history, range).into_iter().collect(),
+            tasks: top(tasks),
+            projects: top(stats::by_project(history, range).into_iter().collect()),
+        }
+    }
+
+    /// Report of the current day or week
+    pub fn current(period: ReportPeriod) -> Result<Self> {
+        Ok(Self::new(&History::load()?, &TaskList::load()?, &Config::load()?, period, days::today()))
+    }
+
+    pub fn title(&self) -> String {
+        match self.period {
+            ReportPeriod::Day => format!("Daily report for {}", self.range.from.format("%A %Y-%m-%d")),
+            ReportPeriod::Week => format!("Weekly report for {} to {}", self.range.from, self.range.to),
+        }
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        let sections = self.sections();
+        match format {
+            ReportFormat::Markdown => to_markdown(&self.title(), &sections),
+            ReportFormat::Html => to_html(&self.title(), &sections),
+            ReportFormat::Text => to_text(&self.title(), &sections),
+        }
+    }
+
+    fn sections(&self) -> Vec<Section> {
+        let last = match self.period {
+            ReportPeriod::Day => "Yesterday",
+            ReportPeriod::Week => "Last week",
+        };
+        let streak = match self.streak {
+            1 => "1 day".to_string(),
+            n => format!("{} days", n),
+        };
+
+        let mut res = vec![Section {
+            title: "Summary",
+            headers: vec!["", "Now", last, "Change"],
+            rows: vec![
+                compare("Pomodoros", self.totals.pomodoros as i64, self.previous.pomodoros as i64, |x| x.to_string()),
+                compare("Focus", self.totals.focus_secs as i64, self.previous.focus_secs as i64, |x| format_secs(x)),
+                compare("Interruptions", self.interruptions as i64, self.previous_interruptions as i64, |x| x.to_string()),
+                vec!["Streak".to_string(), streak, String::new(), String::new()],
+            ],
+        }];
+
+        if self.period == ReportPeriod::Week {
+            res.push(Section {
+                title: "Per day",
+                headers: vec!["Day", "Pomodoros", "Focus"],
+                rows: self.days.iter().map(|(day, totals)| vec![day.format("%a %d").to_string(), totals.pomodoros.to_string(), format_secs(totals.focus_secs as i64)]).collect(),
+            });
+        }
+
+        for (title, list) in [("Top tasks", &self.tasks), ("Top projects", &self.projects)] {
+            res.push(Section {
+                title,
+                headers: vec!["Name", "Pomodoros", "Focus"],
+                rows: list.iter().map(|(name, totals)| vec![name.clone(), totals.pomodoros.to_string(), format_secs(totals.focus_secs as i64)]).collect(),
+            });
+        }
+        res
+    }
+}
+
+/// The groups with the most focus, most focused first
+fn top(mut list: Vec<(String, Totals)>) -> Vec<(String, Totals)> {
+    list.sort_by(|a, b| b.1.focus_secs.cmp(&a.1.focus_secs));
+    list.truncate(TOP);
+    list
+}
+
+/// A summary row with the difference to the previous period
+fn compare(name: &str, now: i64, before: i64, display: impl Fn(i64) -> String) -> Vec<String> {
+    let diff = now - before;
+    let change = match diff {
+        0 => "=".to_string(),
+        d if d > 0 => format!("+{}", display(d)),
+        d => format!("-{}", display(-d)),
+    };
+    vec![name.to_string(), display(now), display(before), change]
+}
+
+fn format_secs(secs: i64) -> String {
+    format!("{}h {:02}m", secs / 3600, (secs / 60) % 60)
+}
+
+impl FromStr for ReportFormat {
+    type Err = ReportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "md" | "markdown" => Ok(ReportFormat::Markdown),
+            "html" => Ok(ReportFormat::Html),
+            "text" | "txt" => Ok(ReportFormat::Text),
+            _ => Err(ReportError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+
+
+// · · ·  Markdown  · · · //
+
+fn to_markdown(title: &str, sections: &[Section]) -> String {
+    let mut res = format!("# {}\n", title);
+    for section in sections {
+        res.push_str(&format!("\n## {}\n\n", section.title));
+        if section.rows.is_empty() {
+            res.push_str("Nothing recorded.\n");
+            continue;
+        }
+
+        let row = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+        res.push_str(&row(section.headers.iter().map(|x| x.to_string()).collect()));
+        res.push_str(&row(section.headers.iter().map(|_| "---".to_string()).collect()));
+        for cells in &section.rows {
+            res.push_str(&row(cells.iter().map(|x| x.replace('|', "\\|")).collect()));
+        }
+    }
+    res
+}
+
+
+
+// · · ·  HTML  · · · //
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; max-width: 40em; margin: 2em auto; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { padding: 0.25em 1em; border-bottom: 1px solid #ddd; text-align: left; }";
+
+fn to_html(title: &str, sections: &[Section]) -> String {
+    let title = html_escape(title);
+    let mut res = format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n<h1>{}</h1>\n", title, HTML_STYLE, title);
+    for section in sections {
+        res.push_str(&format!("<h2>{}</h2>\n", section.title));
+        if section.rows.is_empty() {
+            res.push_str("<p>Nothing recorded.</p>\n");
+            continue;
+        }
+
+        res.push_str("<table>\n<tr>");
+        for header in &section.headers {
+            res.push_str(&format!("<th>{}</th>", header));
+        }
+        res.push_str("</tr>\n");
+        for cells in &section.rows {
+            res.push_str("<tr>");
+            for cell in cells {
+                res.push_str(&format!("<td>{}</td>", html_escape(cell)));
+            }
+            res.push_str("</tr>\n");
+        }
+        res.push_str("</table>\n");
+    }
+    res.push_str("</body>\n</html>\n");
+    res
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+
+
+// · · ·  Plain text  · · · //
+
+fn to_text(title: &str, sections: &[Section]) -> String {
+    let mut res = format!("{}\n{}\n", title, "=".repeat(title.chars().count()));
+    for section in sections {
+        res.push_str(&format!("\n{}\n", section.title));
+        if section.rows.is_empty() {
+            res.push_str("  Nothing recorded.\n");
+            continue;
+        }
+
+        // Every column as wide as its widest cell
+        let mut widths: Vec<usize> = section.headers.iter().map(|x| x.chars().count()).collect();
+        for cells in &section.rows {
+            for (width, cell) in widths.iter_mut().zip(cells) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let row = |cells: Vec<&str>| {
+            let cells: Vec<String> = cells.iter().zip(&widths).enumerate()
+                .map(|(i, (cell, width))| if i == 0 { format!("{:<1$}", cell, width) } else { format!("{:>1$}", cell, width) })
+                .collect();
+            format!("  {}\n", cells.join("  ").trim_end())
+        };
+        res.push_str(&row(section.headers.clone()));
+        for cells in &section.rows {
+            res.push_str(&row(cells.iter().map(|x| x.as_str()).collect()));
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone, Utc};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{history::{Session, Outcome}, pomodoro::PomodoroStage};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// A completed 25 minute pomodoro at noon, so it counts for that day whatever the day start
+    fn session(day: NaiveDate) -> Session {
+        let start = Local.from_local_datetime(&day.and_hms_opt(12, 0, 0).unwrap()).unwrap().with_timezone(&Utc);
+        Session {
+            id: Uuid::new_v4(), stage: PomodoroStage::Work, start, end: start + Duration::minutes(25), focus_secs: 25 * 60, outcome: Outcome::Completed,
+            interruptions: Vec::new(), task: None, reflection: None, project: None, tags: Vec::new(),
+        }
+    }
+
+    fn report(sessions: Vec<Session>, period: ReportPeriod, day: NaiveDate) -> Report {
+        Report::new(&History { sessions, ..Default::default() }, &TaskList::default(), &Config::default(), period, day)
+    }
+
+    fn section(rows: Vec<Vec<&str>>) -> Vec<Section> {
+        vec![Section { title: "Top tasks", headers: vec!["Name", "Pomodoros", "Focus"], rows: rows.into_iter().map(|x| x.into_iter().map(String::from).collect()).collect() }]
+    }
+
+    #[test]
+    fn weeks_run_monday_to_sunday() {
+        // 2023-05-01 is a Monday
+        for day in [date(2023, 5, 1), date(2023, 5, 3), date(2023, 5, 7)] {
+            let report = report(Vec::new(), ReportPeriod::Week, day);
+            assert_eq!((report.range.from, report.range.to), (date(2023, 5, 1), date(2023, 5, 7)));
+            assert_eq!(report.days.len(), 7);
+        }
+        let previous = report(Vec::new(), ReportPeriod::Week, date(2023, 5, 3)).range.previous();
+        assert_eq!((previous.from, previous.to), (date(2023, 4, 24), date(2023, 4, 30)));
+    }
+
+    #[test]
+    fn changes_are_signed_against_the_previous_period() {
+        let sessions = vec![session(date(2023, 5, 2)), session(date(2023, 4, 25)), session(date(2023, 4, 26))];
+        let summary = &report(sessions, ReportPeriod::Week, date(2023, 5, 3)).sections()[0];
+        assert_eq!(summary.rows[0], ["Pomodoros", "1", "2", "-1"]);
+        assert_eq!(summary.rows[1], ["Focus", "0h 25m", "0h 50m", "-0h 25m"]);
+        assert_eq!(summary.rows[2], ["Interruptions", "0", "0", "="]);
+
+        let sessions = vec![session(date(2023, 5, 2)), session(date(2023, 5, 2))];
+        let summary = &report(sessions, ReportPeriod::Day, date(2023, 5, 2)).sections()[0];
+        assert_eq!(summary.headers[2], "Yesterday");
+        assert_eq!(summary.rows[0], ["Pomodoros", "2", "0", "+2"]);
+    }
+
+    #[test]
+    fn markdown_and_html_escape_cells() {
+        let sections = section(vec![vec!["a | b", "1", "0h 25m"], vec!["<b>\"R&D\"</b>", "2", "0h 50m"]]);
+        let markdown = to_markdown("Title", &sections);
+        assert!(markdown.contains("| a \\| b | 1 | 0h 25m |\n"));
+
+        let html = to_html("Q&A", &sections);
+        assert!(html.contains("<title>Q&amp;A</title>"));
+        assert!(html.contains("<td>&lt;b&gt;&quot;R&amp;D&quot;&lt;/b&gt;</td>"));
+        assert!(!html.contains("<b>"));
+    }
+
+    #[test]
+    fn text_columns_fit_their_widest_cell() {
+        let text = to_text("Title", &section(vec![vec!["Write the long chapter", "12", "5h 00m"], vec!["Éditer", "1", "0h 25m"]]));
+        let lines: Vec<&str> = text.lines().skip_while(|x| *x != "Top tasks").skip(1).collect();
+        assert_eq!(lines, [
+            "  Name                    Pomodoros   Focus",
+            "  Write the long chapter         12  5h 00m",
+            "  Éditer                          1  0h 25m",
+        ]);
+        assert!(text.starts_with("Title\n=====\n"));
+    }
+}
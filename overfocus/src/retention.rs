@@ -0,0 +1,85 @@
+use anyhow::Result;
+use chrono::{NaiveDate, Months};
+use uuid::Uuid;
+
+use crate::{history::{History, Rollup}, pomodoro::PomodoroStage, config::Config, edits, days};
+
+/// What pruning did
+pub struct PruneReport {
+    /// Sessions that started before this day were rolled up
+    pub before: NaiveDate,
+    pub sessions: usize,
+    pub days: usize,
+}
+
+/// First day whose sessions are kept when keeping `months` months
+pub fn cutoff(months: u32, today: NaiveDate) -> NaiveDate {
+    today.checked_sub_months(Months::new(months)).unwrap_or(NaiveDate::MIN)
+}
+
+/// Prunes with the retention in the config, if there's one
+pub fn prune_configured() -> Result<Option<PruneReport>> {
+    match Config::load()?.retention_months {
+        Some(months) => Ok(Some(prune(months)?)),
+        None => Ok(None),
+    }
+}
+
+/// Rolls up every session older than `months` months into daily totals
+pub fn prune(months: u32) -> Result<PruneReport> {
+    let before = cutoff(months, days::today());
+    // Nothing to prune is the usual case, which shouldn't rewrite the history every time
+    if !History::load()?.sessions.iter().any(|x| days::local_day(x.start) < before) {
+        return Ok(PruneReport { before, sessions: 0, days: 0 });
+    }
+    History::try_update(|history| {
+        let pruned: Vec<Uuid> = history.sessions.iter().filter(|x| days::local_day(x.start) < before).map(|x| x.id).collect();
+        // Undoing a correction of a pruned session would count it twice
+        edits::forget(&pruned)?;
+        Ok(roll_up(history, before))
+    })
+}
+
+/// Replaces the sessions that count for days before `before` with rollups.<br>
+/// Breaks only count as time off, so they go without a trace.
+/// Rollups from earlier prunes are left as they are, each one stays the total of the sessions it lists.
+pub fn roll_up(history: &mut History, before: NaiveDate) -> PruneReport {
+    let (old, kept) = std::mem::take(&mut history.sessions).into_iter().partition::<Vec<_>, _>(|x| days::local_day(x.start) < before);
+    history.sessions = kept;
+
+    let mut report = PruneReport { before, sessions: old.len(), days: 0 };
+    let mut days = Vec::new();
+    let mut rollups: Vec<Rollup> = Vec::new();
+    for session in old {
+        let day = days::local_day(session.start);
+        if !days.contains(&day) {
+            days.push(day);
+        }
+        if session.stage != PomodoroStage::Work {
+            continue;
+        }
+
+        let mut tags = session.tags.clone();
+        tags.sort();
+        let existing = rollups.iter_mut().find(|x| x.day == day && x.task == session.task && x.project == session.project && x.tags == tags);
+        let rollup = match existing {
+            Some(rollup) => rollup,
+            None => {
+                rollups.push(Rollup { day, task: session.task, project: session.project.clone(), tags, focus_secs: 0, pomodoros: 0, interruptions: 0, sessions: Vec::new() });
+                rollups.last_mut().unwrap()
+            },
+        };
+        rollup.focus_secs += session.focus_secs;
+        rollup.pomodoros += session.is_completed_work() as usize;
+        rollup.interruptions += session.interruptions.len();
+        rollup.sessions.push(session.id);
+    }
+
+    for rollup in &mut rollups {
+        rollup.sessions.sort();
+    }
+    history.rollups.extend(rollups);
+    history.rollups.sort_by_key(|x| x.day);
+    report.days = days.len();
+    report
+}
@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{NaiveDate, Duration};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::{history::{History, Interruption}, pomodoro::PomodoroStage, days::{local_day, local_hour, today}};
+
+/// Label used when grouping sessions without a project
+pub const NO_PROJECT: &str = "(none)";
+
+/// Inclusive range of local days
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+/// Work done by a group of sessions
+#[derive(Clone, Copy, Default)]
+pub struct Totals {
+    pub focus_secs: u64,
+    pub pomodoros: usize,
+}
+
+/// Work of a session or of a rollup of pruned ones, as far as the stats care
+struct Work<'a> {
+    day: NaiveDate,
+    task: Option<Uuid>,
+    project: Option<&'a String>,
+    tags: &'a [String],
+    focus_secs: u64,
+    pomodoros: usize,
+}
+
+fn interruptions(history: &History) -> impl Iterator<Item = &Interruption> {
+    history.sessions.iter().flat_map(|x| x.interruptions.iter())
+}
+
+/// Amount of interruptions logged each day
+pub fn interruptions_per_day(history: &History) -> BTreeMap<NaiveDate, usize> {
+    let mut res = BTreeMap::new();
+    for interruption in interruptions(history) {
+        *res.entry(local_day(interruption.at)).or_insert(0) += 1;
+    }
+    for rollup in history.rollups.iter().filter(|x| x.interruptions > 0) {
+        *res.entry(rollup.day).or_insert(0) += rollup.interruptions;
+    }
+    res
+}
+
+/// Amount of interruptions logged in each hour of the day.<br>
+/// Rollups don't keep the time of their interruptions, so pruned days are left out.
+pub fn interruptions_per_hour(history: &History) -> [usize; 24] {
+    let mut res = [0; 24];
+    for interruption in interruptions(history) {
+        res[local_hour(interruption.at)] += 1;
+    }
+    res
+}
+
+/// Focus time and completed pomodoros of a range of days
+pub fn totals(history: &History, range: DateRange) -> Totals {
+    let mut res = Totals::default();
+    for work in work_in(history, range) {
+        res.add(&work);
+    }
+    res
+}
+
+/// Focus time and completed pomodoros for each day of a range, days without work included
+pub fn per_day(history: &History, range: DateRange) -> BTreeMap<NaiveDate, Totals> {
+    let mut res: BTreeMap<NaiveDate, Totals> = range.days().map(|x| (x, Totals::default())).collect();
+    for work in work_in(history, range) {
+        res.entry(work.day).or_default().add(&work);
+    }
+    res
+}
+
+/// Focus time and completed pomodoros for each task, `None` being work without a task
+pub fn by_task(history: &History, range: DateRange) -> BTreeMap<Option<Uuid>, Totals> {
+    let mut res = BTreeMap::new();
+    for work in work_in(history, range) {
+        res.entry(work.task).or_insert_with(Totals::default).add(&work);
+    }
+    res
+}
+
+/// Amount of interruptions logged in a range of days
+pub fn interruption_count(history: &History, range: DateRange) -> usize {
+    let rolled_up: usize = history.rollups.iter().filter(|x| range.contains(x.day)).map(|x| x.interruptions).sum();
+    interruptions(history).filter(|x| range.contains(local_day(x.at))).count() + rolled_up
+}
+
+/// Every day with at least a completed pomodoro
+pub fn work_days(history: &History) -> BTreeSet<NaiveDate> {
+    work(history).filter(|x| x.pomodoros > 0).map(|x| x.day).collect()
+}
+
+/// Completed pomodoros of every day with any
+pub fn pomodoros_per_day(history: &History) -> BTreeMap<NaiveDate, usize> {
+    let mut res = BTreeMap::new();
+    for work in work(history).filter(|x| x.pomodoros > 0) {
+        *res.entry(work.day).or_insert(0) += work.pomodoros;
+    }
+    res
+}
+
+/// Completed pomodoros of all time
+pub fn pomodoro_count(history: &History) -> usize {
+    work(history).map(|x| x.pomodoros).sum()
+}
+
+/// Focus time and completed pomodoros for each project
+pub fn by_project(history: &History, range: DateRange) -> BTreeMap<String, Totals> {
+    let mut res = BTreeMap::new();
+    for work in work_in(history, range) {
+        let project = work.project.cloned().unwrap_or_else(|| NO_PROJECT.to_string());
+        res.entry(project).or_insert_with(Totals::default).add(&work);
+    }
+    res
+}
+
+/// Focus time and completed pomodoros for each tag, a session counts for every tag it has
+pub fn by_tag(history: &History, range: DateRange) -> BTreeMap<String, Totals> {
+    let mut res = BTreeMap::new();
+    for work in work_in(history, range) {
+        for tag in work.tags {
+            res.entry(tag.clone()).or_insert_with(Totals::default).add(&work);
+        }
+    }
+    res
+}
+
+/// Work sessions and rollups, by the day they count for
+fn work(history: &History) -> impl Iterator<Item = Work<'_>> {
+    let sessions = history.sessions.iter().filter(|x| x.stage == PomodoroStage::Work).map(|x| Work {
+        day: local_day(x.start),
+        task: x.task,
+        project: x.project.as_ref(),
+        tags: &x.tags,
+        focus_secs: x.focus_secs,
+        pomodoros: x.is_completed_work() as usize,
+    });
+    let rollups = history.rollups.iter().map(|x| Work {
+        day: x.day,
+        task: x.task,
+        project: x.project.as_ref(),
+        tags: &x.tags,
+        focus_secs: x.focus_secs,
+        pomodoros: x.pomodoros,
+    });
+    sessions.chain(rollups)
+}
+
+/// Work that counts for a range of days
+fn work_in(history: &History, range: DateRange) -> impl Iterator<Item = Work<'_>> {
+    work(history).filter(move |x| range.contains(x.day))
+}
+
+impl DateRange {
+    /// The last `days` days, today included
+    pub fn last_days(days: i64) -> Self {
+        let to = today();
+        Self { from: to - Duration::days(days - 1), to }
+    }
+
+    pub fn contains(&self, day: NaiveDate) -> bool {
+        self.from <= day && day <= self.to
+    }
+
+    /// Every day in the range, in order
+    pub fn days(&self) -> impl Iterator<Item = NaiveDate> {
+        let to = self.to;
+        self.from.iter_days().take_while(move |x| *x <= to)
+    }
+
+    /// The range of the same length right before this one
+    pub fn previous(&self) -> Self {
+        let len = self.to - self.from + Duration::days(1);
+        Self { from: self.from - len, to: self.to - len }
+    }
+}
+
+impl Totals {
+    fn add(&mut self, work: &Work) {
+        self.focus_secs += work.focus_secs;
+        self.pomodoros += work.pomodoros;
+    }
+}
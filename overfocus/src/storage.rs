@@ -0,0 +1,311 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use anyhow::Result;
+use directories::ProjectDirs;
+use chrono::Local;
+use serde::{de::DeserializeOwned, Serialize, Deserialize};
+use serde_json::{Value, json};
+use thiserror::Error;
+
+/// Environment variable that overrides where overfocus stores its files
+pub const DATA_DIR_ENV: &str = "OVERFOCUS_DATA_DIR";
+
+/// Version of the data directory format, bumped by every migration
+pub const SCHEMA_VERSION: u32 = 4;
+
+const SCHEMA_FILE: &str = "schema.json";
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("Couldn't find a home directory to store overfocus data in")]
+    NoHomeDirectory,
+    #[error("The data in {0} was written by a newer overfocus (schema v{1}, this one knows up to v{SCHEMA_VERSION}), update overfocus to use it")]
+    NewerSchema(PathBuf, u32),
+    #[error("Migration to schema v{0} failed, the data from before it is in {1}: {2}")]
+    MigrationFailed(u32, PathBuf, anyhow::Error),
+}
+
+#[derive(Serialize, Deserialize)]
+struct Schema {
+    version: u32,
+}
+
+/// A change to the files of the data directory, from the version right before `version`
+struct Migration {
+    version: u32,
+    run: fn(&Path) -> Result<()>,
+}
+
+/// Every migration, oldest first.<br>
+/// They work on the json and the file names as they were, rather than on the types, which keep changing after them.
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, run: fill_fields },
+    Migration { version: 2, run: move_sessions_to_log },
+    Migration { version: 3, run: add_rollups },
+    Migration { version: 4, run: allow_skipped },
+];
+
+/// What a migration did
+pub struct MigrationReport {
+    pub from: u32,
+    pub to: u32,
+    /// Where the files were copied before migrating
+    pub backup: PathBuf,
+}
+
+/// Returns the directory where all the persistent data lives, creating it if needed
+pub fn data_dir() -> Result<PathBuf> {
+    let dir = match std::env::var_os(DATA_DIR_ENV) {
+        Some(dir) => PathBuf::from(dir),
+        None => ProjectDirs::from("", "", "overfocus").ok_or(StorageError::NoHomeDirectory)?.data_dir().to_path_buf(),
+    };
+
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Brings the data directory up to the current schema, backing its files up first.<br>
+/// Returns what was done, if anything had to be.
+pub fn migrate() -> Result<Option<MigrationReport>> {
+    migrate_dir(&data_dir()?)
+}
+
+fn migrate_dir(dir: &Path) -> Result<Option<MigrationReport>> {
+    let schema_path = dir.join(SCHEMA_FILE);
+    let version = match schema_path.exists() {
+        true => schema_version(dir)?,
+        // A fresh directory has nothing to migrate
+        false if json_files(dir)?.is_empty() => SCHEMA_VERSION,
+        false => 0,
+    };
+
+    if version > SCHEMA_VERSION {
+        Err(StorageError::NewerSchema(dir.to_path_buf(), version))?;
+    }
+    if version == SCHEMA_VERSION {
+        if !schema_path.exists() {
+            save_to(&schema_path, &Schema { version })?;
+        }
+        return Ok(None);
+    }
+
+    let backup = backup(dir, version)?;
+    for migration in MIGRATIONS.iter().filter(|x| x.version > version) {
+        if let Err(e) = (migration.run)(dir) {
+            Err(StorageError::MigrationFailed(migration.version, backup.clone(), e))?;
+        }
+        save_to(&schema_path, &Schema { version: migration.version })?;
+    }
+
+    Ok(Some(MigrationReport { from: version, to: SCHEMA_VERSION, backup }))
+}
+
+/// Schema version of a data directory, or of a copy of it
+pub fn schema_version(dir: &Path) -> Result<u32> {
+    Ok(serde_json::from_str::<Schema>(&fs::read_to_string(dir.join(SCHEMA_FILE))?)?.version)
+}
+
+/// Marks a copy of the data directory as written with the current schema
+pub fn stamp_schema(dir: &Path) -> Result<()> {
+    save_to(&dir.join(SCHEMA_FILE), &Schema { version: SCHEMA_VERSION })
+}
+
+/// Copies every json file of the data directory into a new folder under `backups`
+fn backup(dir: &Path, version: u32) -> Result<PathBuf> {
+    let backup = dir.join("backups").join(format!("schema-v{}-{}", version, Local::now().format("%Y%m%d-%H%M%S")));
+    fs::create_dir_all(&backup)?;
+    for file in json_files(dir)? {
+        fs::copy(&file, backup.join(file.file_name().unwrap()))?;
+    }
+    Ok(backup)
+}
+
+/// Every json and json lines file directly inside a directory
+pub fn json_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut res = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().map_or(false, |x| x == "json" || x == "jsonl") {
+            res.push(path);
+        }
+    }
+    Ok(res)
+}
+
+/// Loads a json file from the data directory, or the default value if it doesn't exist yet
+pub fn load<T: DeserializeOwned + Default>(file: &str) -> Result<T> {
+    load_from(&data_dir()?.join(file))
+}
+
+/// Saves a value as json in the data directory
+pub fn save<T: Serialize>(file: &str, data: &T) -> Result<()> {
+    save_to(&data_dir()?.join(file), data)
+}
+
+/// Loads a json file from anywhere, or the default value if it doesn't exist
+pub fn load_from<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+
+    let text = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+/// Saves a value as json anywhere.<br>
+/// The file is written to a temporary path first so a crash never leaves it half written.
+pub fn save_to<T: Serialize>(path: &Path, data: &T) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+
+    fs::write(&tmp, serde_json::to_string_pretty(data)?)?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+
+
+// · · ·  Migrations  · · · //
+
+/// v1: data from before versioning gets every field sessions and tasks gained along the way,
+/// so it doesn't rely on defaults of fields that may change meaning later
+fn fill_fields(dir: &Path) -> Result<()> {
+    let fill = |item: &mut Value, fields: &[(&str, Value)]| {
+        if let Some(item) = item.as_object_mut() {
+            for (field, default) in fields {
+                item.entry(*field).or_insert_with(|| default.clone());
+            }
+        }
+    };
+
+    if let Some(mut history) = load_value(dir, "history.json")? {
+        for session in history["sessions"].as_array_mut().into_iter().flatten() {
+            fill(session, &[("interruptions", json!([])), ("task", Value::Null), ("reflection", Value::Null), ("project", Value::Null), ("tags", json!([]))]);
+        }
+        save_to(&dir.join("history.json"), &history)?;
+    }
+    if let Some(mut tasks) = load_value(dir, "tasks.json")? {
+        for task in tasks["tasks"].as_array_mut().into_iter().flatten() {
+            fill(task, &[("project", Value::Null), ("tags", json!([])), ("priority", Value::Null), ("source", Value::Null)]);
+        }
+        save_to(&dir.join("tasks.json"), &tasks)?;
+    }
+    Ok(())
+}
+
+/// v2: sessions are appended to an event log, the history file being a snapshot of what it held when last compacted.<br>
+/// The sessions move to the log as finished stages, so the snapshot starts out empty.
+fn move_sessions_to_log(dir: &Path) -> Result<()> {
+    let Some(mut history) = load_value(dir, "history.json")? else { return Ok(()) };
+    let log = dir.join("history.jsonl");
+    // A log that's already there came from a version that had one, so it's left as it is
+    if log.exists() {
+        return Ok(());
+    }
+
+    let sessions = history["sessions"].take();
+    let mut text = String::new();
+    for (i, session) in sessions.as_array().into_iter().flatten().enumerate() {
+        text += &serde_json::to_string(&json!({ "seq": i + 1, "event": "stage_finished", "session": session }))?;
+        text.push('\n');
+    }
+    fs::write(&log, text)?;
+
+    history["sessions"] = json!([]);
+    history["last_event"] = json!(0);
+    save_to(&dir.join("history.json"), &history)
+}
+
+/// v3: sessions past the retention are rolled up into daily totals kept along with the history
+fn add_rollups(dir: &Path) -> Result<()> {
+    let Some(mut history) = load_value(dir, "history.json")? else { return Ok(()) };
+    if let Some(history) = history.as_object_mut() {
+        history.entry("rollups").or_insert_with(|| json!([]));
+    }
+    save_to(&dir.join("history.json"), &history)
+}
+
+/// v4: stages can be abandoned by skipping them, which versions before it can't read.<br>
+/// The files stay as they are, the bump only makes those versions refuse the data instead of failing to read it.
+fn allow_skipped(_dir: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// A json file of a directory as a value, if it exists
+fn load_value(dir: &Path, file: &str) -> Result<Option<Value>> {
+    let path = dir.join(file);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{history::{History, Session}, tasks::TaskList, testing::temp_dir};
+
+    /// Data as written before versioning, with sessions and tasks from before most of their fields
+    fn v0_fixture(dir: &Path) {
+        let history = json!({ "sessions": [
+            { "id": Uuid::new_v4(), "stage": "Work", "start": "2023-05-01T09:00:00Z", "end": "2023-05-01T09:25:00Z", "focus_secs": 1500, "outcome": "Completed" },
+            { "id": Uuid::new_v4(), "stage": "ShortBreak", "start": "2023-05-01T09:25:00Z", "end": "2023-05-01T09:27:00Z", "focus_secs": 120, "outcome": { "Abandoned": "Stopped" } },
+        ]});
+        let tasks = json!({ "tasks": [{ "id": Uuid::new_v4(), "title": "Write", "estimate": 2, "completed": 1, "done": false }] });
+        fs::write(dir.join("history.json"), history.to_string()).unwrap();
+        fs::write(dir.join("tasks.json"), tasks.to_string()).unwrap();
+    }
+
+    #[test]
+    fn migrates_v0() {
+        let dir = temp_dir();
+        v0_fixture(&dir);
+
+        let report = migrate_dir(&dir).unwrap().unwrap();
+        assert_eq!((report.from, report.to), (0, SCHEMA_VERSION));
+        assert_eq!(schema_version(&dir).unwrap(), SCHEMA_VERSION);
+        for file in ["history.json", "tasks.json"] {
+            assert!(report.backup.join(file).exists());
+        }
+
+        // The sessions wait in the log, as finished stages after an empty snapshot
+        let snapshot: History = load_from(&dir.join("history.json")).unwrap();
+        assert!(snapshot.sessions.is_empty());
+        assert!(snapshot.rollups.is_empty());
+        let log = fs::read_to_string(dir.join("history.jsonl")).unwrap();
+        let entries: Vec<Value> = log.lines().map(|x| serde_json::from_str(x).unwrap()).collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1]["seq"], 2);
+        assert_eq!(entries[1]["event"], "stage_finished");
+        let session: Session = serde_json::from_value(entries[0]["session"].clone()).unwrap();
+        assert_eq!(session.focus_secs, 1500);
+        assert!(session.tags.is_empty());
+
+        let tasks: TaskList = load_from(&dir.join("tasks.json")).unwrap();
+        assert_eq!(tasks.tasks[0].title, "Write");
+        assert!(tasks.tasks[0].source.is_none());
+
+        // Once current there's nothing left to do
+        assert!(migrate_dir(&dir).unwrap().is_none());
+    }
+
+    #[test]
+    fn fresh_directories_start_current() {
+        let dir = temp_dir();
+        assert!(migrate_dir(&dir).unwrap().is_none());
+        assert_eq!(schema_version(&dir).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn refuses_newer_schemas() {
+        let dir = temp_dir();
+        v0_fixture(&dir);
+        fs::write(dir.join(SCHEMA_FILE), json!({ "version": SCHEMA_VERSION + 1 }).to_string()).unwrap();
+
+        let err = migrate_dir(&dir).err().unwrap();
+        assert!(matches!(err.downcast_ref::<StorageError>(), Some(StorageError::NewerSchema(_, v)) if *v == SCHEMA_VERSION + 1));
+        // Nothing was touched
+        assert!(!dir.join("backups").exists());
+    }
+}
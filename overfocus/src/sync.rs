@@ -0,0 +1,481 @@
+use std::{fs, path::{Path, PathBuf}, process::Command, collections::{HashMap, HashSet}, hash::Hash};
+
+use anyhow::Result;
+use chrono::Local;
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+
+use crate::{storage::{self, StorageError, SCHEMA_VERSION}, config::Config, edits, history::{History, Session, Rollup, HISTORY_FILE}, tasks::{TaskList, Task, TASKS_FILE}, achievements::{Achievements, ACHIEVEMENTS_FILE}};
+
+/// What the data looked like after the last sync, to tell which side changed what
+const BASE_FILE: &str = "sync-base.json";
+
+#[derive(Error, Debug)]
+pub enum SyncError {
+    #[error("No sync folder is set, pass one with `overfocus sync PATH`")]
+    NotConfigured,
+    #[error("{0} is not a folder")]
+    NotAFolder(PathBuf),
+    #[error("{0} has uncommitted changes, commit or discard them before syncing")]
+    Uncommitted(PathBuf),
+    #[error("`git {0}` failed: {1}")]
+    Git(String, String),
+}
+
+/// The data as it was when it was last synced with `dir`
+#[derive(Default, Serialize, Deserialize)]
+struct SyncBase {
+    dir: Option<PathBuf>,
+    sessions: Vec<Session>,
+    tasks: Vec<Task>,
+}
+
+/// What a sync did
+pub struct SyncReport {
+    pub dir: PathBuf,
+    /// Sessions and tasks changed here with what came from the folder
+    pub pulled: usize,
+    /// Sessions and tasks changed in the folder with what was done here
+    pub pushed: usize,
+    /// Changes made on both sides that couldn't be merged
+    pub conflicts: Vec<String>,
+}
+
+/// Result of merging two lists that share an ancestor
+struct Merged<T> {
+    items: Vec<T>,
+    pulled: usize,
+    pushed: usize,
+    /// Local versions of the items that lost a conflict
+    conflicts: Vec<T>,
+}
+
+/// Syncs with the folder in the config
+pub fn sync_configured() -> Result<SyncReport> {
+    let config = Config::load()?;
+    let dir = config.sync_dir.clone().ok_or(SyncError::NotConfigured)?;
+    sync(&dir, &machine_name(&config))
+}
+
+/// Merges the history, the tasks and the achievements with the ones in `dir`, writes the result to both sides
+/// and mirrors every file of the data directory to `dir/machines/<machine>`.<br>
+/// If `dir` is a git repository the changes are committed, and if it tracks a remote it's pulled first and pushed after.
+pub fn sync(dir: &Path, machine: &str) -> Result<SyncReport> {
+    if !dir.is_dir() {
+        Err(SyncError::NotAFolder(dir.to_path_buf()))?;
+    }
+    let dir = dir.canonicalize()?;
+    let is_repo = dir.join(".git").exists();
+    let tracks_remote = is_repo && git(&dir, &["rev-parse", "--abbrev-ref", "@{u}"]).is_ok();
+    // Changes made in the folder by hand would be overwritten or committed as the sync's
+    if is_repo && !git(&dir, &["status", "--porcelain"])?.trim().is_empty() {
+        Err(SyncError::Uncommitted(dir.clone()))?;
+    }
+    if tracks_remote {
+        // Commits left from a failed push go on top of the remote ones, and stay for next time if they don't fit
+        if let Err(e) = git(&dir, &["pull", "--rebase"]) {
+            let _ = git(&dir, &["rebase", "--abort"]);
+            return Err(e);
+        }
+    }
+
+    // Whatever wrote the folder has to be understood before merging it
+    if dir.join("schema.json").exists() {
+        let version = storage::schema_version(&dir)?;
+        if version > SCHEMA_VERSION {
+            Err(StorageError::NewerSchema(dir.clone(), version))?;
+        }
+    }
+
+    // A base from another folder says nothing about this one, so everything is merged as new
+    let mut base: SyncBase = storage::load(BASE_FILE)?;
+    if base.dir.as_ref() != Some(&dir) {
+        base = SyncBase::default();
+    }
+    let remote_history: History = storage::load_from(&dir.join(HISTORY_FILE))?;
+    let remote_tasks: TaskList = storage::load_from(&dir.join(TASKS_FILE))?;
+    let remote_achievements: Achievements = storage::load_from(&dir.join(ACHIEVEMENTS_FILE))?;
+    let mut report = SyncReport { dir: dir.clone(), pulled: 0, pushed: 0, conflicts: Vec::new() };
+
+    let (sessions, rollups) = History::try_update(|history| {
+        let merged = merge_rollups(&base.sessions, &history.rollups, &remote_history.rollups);
+        history.rollups = merged.items;
+        history.rollups.sort_by_key(|x| x.day);
+        report.pulled += merged.pulled;
+        report.pushed += merged.pushed;
+        for rollup in &merged.conflicts {
+            report.conflicts.push(format!("the totals of {} couldn't be merged exactly, both sides were kept so some sessions may count twice", rollup.day));
+        }
+
+        let merged = merge(&base.sessions, &history.sessions, &remote_history.sessions, |x| x.id, |_, _, theirs| (theirs.clone(), true));
+        history.sessions = merged.items;
+        history.sessions.sort_by_key(|x| x.start);
+        report.pulled += merged.pulled;
+        report.pushed += merged.pushed;
+
+        // The local side of a conflict goes to the edit log, so undoing brings it back
+        for session in &merged.conflicts {
+            let start = session.start.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+            report.conflicts.push(format!("the session from {} was changed on both sides, kept the synced version", start));
+        }
+        if !merged.conflicts.is_empty() {
+            let ids = merged.conflicts.iter().map(|x| x.id).collect();
+            edits::log("Sync", merged.conflicts, ids)?;
+        }
+        Ok((history.sessions.clone(), history.rollups.clone()))
+    })?;
+
+    let tasks = TaskList::update(|list| {
+        let merged = merge(&base.tasks, &list.tasks, &remote_tasks.tasks, |x| x.id, merge_task);
+        list.tasks = merged.items;
+        report.pulled += merged.pulled;
+        report.pushed += merged.pushed;
+        for task in &merged.conflicts {
+            report.conflicts.push(format!("the task `{}` was changed on both sides, kept the synced details", task.title));
+        }
+        list.tasks.clone()
+    })?;
+    // Milestones are only ever unlocked, so both sides simply get all of them
+    let achievements = Achievements::merge(&remote_achievements)?;
+
+    storage::save_to(&dir.join(HISTORY_FILE), &History { sessions: sessions.clone(), rollups: rollups.clone(), ..Default::default() })?;
+    storage::save_to(&dir.join(TASKS_FILE), &TaskList { tasks: tasks.clone() })?;
+    storage::save_to(&dir.join(ACHIEVEMENTS_FILE), &achievements)?;
+    storage::stamp_schema(&dir)?;
+    mirror(&dir.join("machines").join(machine))?;
+
+    if is_repo {
+        git(&dir, &["add", "-A"])?;
+        if !git(&dir, &["status", "--porcelain"])?.trim().is_empty() {
+            git(&dir, &["commit", "-m", &format!("Sync from {}", machine)])?;
+        }
+        if tracks_remote {
+            git(&dir, &["push"])?;
+        }
+    }
+
+    // Only once the folder surely has the changes, or a failed sync would look like deletions next time
+    storage::save(BASE_FILE, &SyncBase { dir: Some(dir.clone()), sessions, tasks })?;
+    Ok(report)
+}
+
+/// The name of this machine, from the config or else the host name
+pub fn machine_name(config: &Config) -> String {
+    let name = config.machine.clone()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .unwrap_or_default();
+
+    // It names a folder, so only the safe characters are kept
+    let name: String = name.trim().chars().filter(|x| x.is_ascii_alphanumeric() || matches!(x, '-' | '_' | '.')).collect();
+    if name.is_empty() || name.starts_with('.') { "this-machine".to_string() } else { name }
+}
+
+/// Three-way merge of two lists by key, with `base` as what both had in common.<br>
+/// Items changed on one side only take that change, deletions included, and
+/// items changed on both sides are merged by `resolve`, which also tells if local changes were lost.
+fn merge<T: Clone + Serialize, K: Eq + Hash>(base: &[T], local: &[T], remote: &[T], id: fn(&T) -> K, resolve: fn(Option<&T>, &T, &T) -> (T, bool)) -> Merged<T> {
+    // Items are compared through their json, which covers every field
+    let same = |a: Option<&T>, b: Option<&T>| a.map(|x| serde_json::to_value(x).ok()) == b.map(|x| serde_json::to_value(x).ok());
+    let base: HashMap<K, &T> = base.iter().map(|x| (id(x), x)).collect();
+    let remote_ids: HashMap<K, &T> = remote.iter().map(|x| (id(x), x)).collect();
+    let mut res = Merged { items: Vec::new(), pulled: 0, pushed: 0, conflicts: Vec::new() };
+
+    for item in local {
+        let (old, theirs) = (base.get(&id(item)).copied(), remote_ids.get(&id(item)).copied());
+        match theirs {
+            Some(theirs) if same(Some(item), Some(theirs)) => res.items.push(item.clone()),
+            Some(theirs) if same(old, Some(item)) => {
+                res.items.push(theirs.clone());
+                res.pulled += 1;
+            },
+            Some(theirs) if same(old, Some(theirs)) => {
+                res.items.push(item.clone());
+                res.pushed += 1;
+            },
+            Some(theirs) => {
+                let (merged, lost) = resolve(old, item, theirs);
+                res.pulled += !same(Some(&merged), Some(item)) as usize;
+                res.pushed += !same(Some(&merged), Some(theirs)) as usize;
+                res.items.push(merged);
+                if lost {
+                    res.conflicts.push(item.clone());
+                }
+            },
+            // Deleted remotely, unless it changed here since
+            None if old.is_some() && same(old, Some(item)) => res.pulled += 1,
+            None => {
+                res.items.push(item.clone());
+                res.pushed += 1;
+            },
+        }
+    }
+
+    let local_ids: HashMap<K, &T> = local.iter().map(|x| (id(x), x)).collect();
+    for item in remote.iter().filter(|x| !local_ids.contains_key(&id(x))) {
+        let old = base.get(&id(item)).copied();
+        // Deleted here, unless it changed remotely since
+        if old.is_some() && same(old, Some(item)) {
+            res.pushed += 1;
+        } else {
+            res.items.push(item.clone());
+            res.pulled += 1;
+        }
+    }
+    res
+}
+
+/// Merges a task changed on both sides field by field.<br>
+/// Pomodoros credited on either side all count, and fields changed differently on both sides keep the remote value.
+fn merge_task(old: Option<&Task>, ours: &Task, theirs: &Task) -> (Task, bool) {
+    let mut lost = false;
+    let task = Task {
+        id: theirs.id,
+        title: pick(old.map(|x| &x.title), &ours.title, &theirs.title, &mut lost),
+        estimate: pick(old.map(|x| &x.estimate), &ours.estimate, &theirs.estimate, &mut lost),
+        completed: match old {
+            Some(old) => (ours.completed as i32 + theirs.completed as i32 - old.completed as i32).clamp(0, u8::MAX as i32) as u8,
+            // Without knowing where they started, the side that got further has likely seen the other's credits
+            None => ours.completed.max(theirs.completed),
+        },
+        // A task is only finished once, so both sides finishing it agree
+        done: match old {
+            Some(old) => pick(Some(&old.done), &ours.done, &theirs.done, &mut lost),
+            None => ours.done || theirs.done,
+        },
+        project: pick(old.map(|x| &x.project), &ours.project, &theirs.project, &mut lost),
+        tags: pick(old.map(|x| &x.tags), &ours.tags, &theirs.tags, &mut lost),
+        priority: pick(old.map(|x| &x.priority), &ours.priority, &theirs.priority, &mut lost),
+        source: pick(old.map(|x| &x.source), &ours.source, &theirs.source, &mut lost),
+    };
+    (task, lost)
+}
+
+/// Three-way merge of a single field, the remote value wins if both sides changed it
+fn pick<V: PartialEq + Clone>(old: Option<&V>, ours: &V, theirs: &V, lost: &mut bool) -> V {
+    match old {
+        Some(old) if old == theirs => ours.clone(),
+        Some(old) if old == ours => theirs.clone(),
+        _ => {
+            *lost |= ours != theirs;
+            theirs.clone()
+        },
+    }
+}
+
+/// Merges the rollups of both sides, which are never changed once made but may total some of the same sessions.<br>
+/// A rollup whose sessions another one covers gives way to it, and one sharing only some of them
+/// is kept less the shared sessions, as `base` has them.
+/// When that can't be told, both are kept, since counting work twice beats losing it.
+fn merge_rollups(base: &[Session], local: &[Rollup], remote: &[Rollup]) -> Merged<Rollup> {
+    let same_group = |a: &Rollup, b: &Rollup| a.day == b.day && a.task == b.task && a.project == b.project && a.tags == b.tags;
+    let same = |a: &Rollup, b: &Rollup| serde_json::to_value(a).ok() == serde_json::to_value(b).ok();
+    let mut res = Merged { items: local.to_vec(), pulled: 0, pushed: 0, conflicts: Vec::new() };
+
+    for theirs in remote {
+        if res.items.iter().any(|x| same(x, theirs)) {
+            continue;
+        }
+        // Rollups from before sessions were listed can only be told apart by their group
+        if theirs.sessions.is_empty() {
+            if res.items.iter().any(|x| x.sessions.is_empty() && same_group(x, theirs)) {
+                res.conflicts.push(theirs.clone());
+            }
+            res.items.push(theirs.clone());
+            continue;
+        }
+
+        let ids: HashSet<_> = theirs.sessions.iter().collect();
+        if res.items.iter().any(|x| ids.iter().all(|id| x.sessions.contains(id))) {
+            continue;
+        }
+        res.items.retain(|x| x.sessions.is_empty() || !x.sessions.iter().all(|id| ids.contains(id)));
+
+        let mut theirs = theirs.clone();
+        let shared: Vec<_> = theirs.sessions.iter().copied().filter(|id| res.items.iter().any(|x| x.sessions.contains(id))).collect();
+        for id in shared {
+            match base.iter().find(|x| x.id == id) {
+                Some(session) => {
+                    theirs.focus_secs = theirs.focus_secs.saturating_sub(session.focus_secs);
+                    theirs.pomodoros = theirs.pomodoros.saturating_sub(session.is_completed_work() as usize);
+                    theirs.interruptions = theirs.interruptions.saturating_sub(session.interruptions.len());
+                    theirs.sessions.retain(|x| *x != id);
+                },
+                None => {
+                    res.conflicts.push(theirs.clone());
+                    break;
+                },
+            }
+        }
+        if !theirs.sessions.is_empty() {
+            res.items.push(theirs);
+        }
+    }
+
+    res.pulled = res.items.iter().filter(|x| !local.iter().any(|y| same(x, y))).count();
+    res.pushed = res.items.iter().filter(|x| !remote.iter().any(|y| same(x, y))).count();
+    res
+}
+
+/// Copies every file of the data directory into `dir`
+fn mirror(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    for file in storage::json_files(&storage::data_dir()?)? {
+        let name = file.file_name().unwrap();
+        if name != BASE_FILE {
+            fs::copy(&file, dir.join(name))?;
+        }
+    }
+    Ok(())
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()
+        .map_err(|e| SyncError::Git(args.join(" "), e.to_string()))?;
+    if !output.status.success() {
+        Err(SyncError::Git(args.join(" "), String::from_utf8_lossy(&output.stderr).trim().to_string()))?;
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{history::Outcome, pomodoro::PomodoroStage};
+
+    fn task(title: &str, completed: u8) -> Task {
+        Task { id: Uuid::new_v4(), title: title.to_string(), estimate: 4, completed, done: false, project: None, tags: Vec::new(), priority: None, source: None }
+    }
+
+    fn session(focus_secs: u64) -> Session {
+        let start = Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap();
+        Session {
+            id: Uuid::new_v4(), stage: PomodoroStage::Work, start, end: start, focus_secs, outcome: Outcome::Completed,
+            interruptions: Vec::new(), task: None, reflection: None, project: None, tags: Vec::new(),
+        }
+    }
+
+    fn rollup(sessions: &[&Session]) -> Rollup {
+        let mut ids: Vec<Uuid> = sessions.iter().map(|x| x.id).collect();
+        ids.sort();
+        Rollup {
+            day: NaiveDate::from_ymd_opt(2023, 5, 1).unwrap(), task: None, project: None, tags: Vec::new(),
+            focus_secs: sessions.iter().map(|x| x.focus_secs).sum(), pomodoros: sessions.len(), interruptions: 0, sessions: ids,
+        }
+    }
+
+    fn titles(tasks: &[Task]) -> Vec<&str> {
+        let mut res: Vec<&str> = tasks.iter().map(|x| x.title.as_str()).collect();
+        res.sort();
+        res
+    }
+
+    fn merge_tasks(base: &[Task], local: &[Task], remote: &[Task]) -> Merged<Task> {
+        merge(base, local, remote, |x| x.id, merge_task)
+    }
+
+    #[test]
+    fn takes_changes_from_either_side() {
+        let (kept, renamed, deleted) = (task("Kept", 0), task("Old name", 0), task("Deleted", 0));
+        let base = vec![kept.clone(), renamed.clone(), deleted.clone()];
+        let local = vec![kept.clone(), renamed.clone(), task("New here", 0)];
+        let remote = vec![kept.clone(), Task { title: "New name".to_string(), ..renamed }, deleted, task("New there", 0)];
+
+        let merged = merge_tasks(&base, &local, &remote);
+        assert_eq!(titles(&merged.items), ["Kept", "New here", "New name", "New there"]);
+        assert_eq!((merged.pulled, merged.pushed), (2, 2));
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn keeps_changed_items_over_deletions() {
+        let task = task("Write", 0);
+        let changed = Task { completed: 1, ..task.clone() };
+        let merged = merge_tasks(&[task], &[changed], &[]);
+        assert_eq!(titles(&merged.items), ["Write"]);
+    }
+
+    #[test]
+    fn counts_credits_from_both_sides() {
+        let base = task("Write", 1);
+        let local = Task { completed: 2, ..base.clone() };
+        let remote = Task { completed: 3, title: "Write more".to_string(), ..base.clone() };
+
+        let merged = merge_tasks(&[base], &[local], &[remote]);
+        assert_eq!(merged.items[0].completed, 4);
+        assert_eq!(merged.items[0].title, "Write more");
+        assert!(merged.conflicts.is_empty());
+    }
+
+    #[test]
+    fn reports_fields_changed_on_both_sides() {
+        let base = task("Write", 0);
+        let local = Task { title: "Write here".to_string(), done: true, ..base.clone() };
+        let remote = Task { title: "Write there".to_string(), completed: 1, ..base.clone() };
+
+        let merged = merge_tasks(&[base], &[local], &[remote]);
+        let task = &merged.items[0];
+        assert_eq!((task.title.as_str(), task.completed, task.done), ("Write there", 1, true));
+        assert_eq!(merged.conflicts[0].title, "Write here");
+    }
+
+    #[test]
+    fn keeps_the_most_credits_without_a_base() {
+        let local = task("Write", 3);
+        let remote = Task { completed: 1, done: true, ..local.clone() };
+        let merged = merge_tasks(&[], &[local], &[remote]);
+        assert_eq!((merged.items[0].completed, merged.items[0].done), (3, true));
+    }
+
+    #[test]
+    fn rollups_of_the_same_sessions_count_once() {
+        let (a, b, c) = (session(1500), session(1200), session(600));
+        let local = vec![rollup(&[&a, &b])];
+
+        let merged = merge_rollups(&[], &local, &[rollup(&[&a, &b])]);
+        assert_eq!(merged.items.len(), 1);
+        assert_eq!((merged.pulled, merged.pushed), (0, 0));
+
+        // The side that rolled up more sessions has the other's in its totals
+        let merged = merge_rollups(&[], &local, &[rollup(&[&a])]);
+        assert_eq!(merged.items[0].focus_secs, 2700);
+        let merged = merge_rollups(&[], &local, &[rollup(&[&a, &b, &c])]);
+        assert_eq!((merged.items.len(), merged.items[0].focus_secs), (1, 3300));
+    }
+
+    #[test]
+    fn rollups_of_different_sessions_add_up() {
+        let (a, b, c) = (session(1500), session(1200), session(600));
+        let total = |merged: &Merged<Rollup>| merged.items.iter().map(|x| x.focus_secs).sum::<u64>();
+
+        let merged = merge_rollups(&[], &[rollup(&[&a])], &[rollup(&[&b])]);
+        assert_eq!(total(&merged), 2700);
+        assert_eq!((merged.pulled, merged.pushed), (1, 1));
+
+        // A session both had before pruning counts once, as long as the last sync saw it
+        let merged = merge_rollups(&[a.clone()], &[rollup(&[&a, &b])], &[rollup(&[&a, &c])]);
+        assert_eq!(total(&merged), 3300);
+        assert!(merged.conflicts.is_empty());
+        let merged = merge_rollups(&[], &[rollup(&[&a, &b])], &[rollup(&[&a, &c])]);
+        assert_eq!(merged.conflicts.len(), 1);
+    }
+
+    #[test]
+    fn differing_rollups_without_sessions_are_both_kept() {
+        let unlisted = |sessions: &[&Session]| Rollup { sessions: Vec::new(), ..rollup(sessions) };
+        let (a, b, c) = (session(1500), session(1200), session(600));
+
+        let merged = merge_rollups(&[], &[unlisted(&[&a, &b])], &[unlisted(&[&c])]);
+        assert_eq!(merged.items.iter().map(|x| x.pomodoros).sum::<usize>(), 3);
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!((merged.pulled, merged.pushed), (1, 1));
+
+        // Nothing to merge when both sides have the same totals
+        let merged = merge_rollups(&[], &[unlisted(&[&a])], &[unlisted(&[&a])]);
+        assert_eq!((merged.items.len(), merged.conflicts.len()), (1, 0));
+    }
+}
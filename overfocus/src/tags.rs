@@ -0,0 +1,24 @@
+/// Splits user written tags, separated by commas or spaces and with or without the `@` they're shown with, removing duplicates
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut res: Vec<String> = Vec::new();
+    for tag in text.split(|c: char| c == ',' || c.is_whitespace()).map(|x| x.trim_start_matches('@')) {
+        if !tag.is_empty() && !res.iter().any(|x| x == tag) {
+            res.push(tag.to_string());
+        }
+    }
+    res
+}
+
+/// Writes tags back in the format `parse_tags` reads
+pub fn display_tags(tags: &[String]) -> String {
+    tags.join(", ")
+}
+
+/// Adds the tags from `other` that aren't in `tags` yet
+pub fn merge_tags(tags: &mut Vec<String>, other: &[String]) {
+    for tag in other {
+        if !tags.contains(tag) {
+            tags.push(tag.clone());
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::Mutex;
+
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::{storage, import::TaskSource};
+
+pub(crate) const TASKS_FILE: &str = "tasks.json";
+
+/// Serializes every read-modify-write of the tasks file
+static TASKS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Something to work on, with the pomodoros it was estimated to take and the ones it actually took
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: Uuid,
+    pub title: String,
+    pub estimate: u8,
+    pub completed: u8,
+    pub done: bool,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Priority from `A` (highest) to `Z`, as in todo.txt
+    #[serde(default)]
+    pub priority: Option<char>,
+    /// The file the task was imported from
+    #[serde(default)]
+    pub source: Option<TaskSource>,
+}
+
+/// Every task, persisted in the data directory
+#[derive(Default, Serialize, Deserialize)]
+pub struct TaskList {
+    pub tasks: Vec<Task>,
+}
+
+impl TaskList {
+    pub fn load() -> Result<Self> {
+        let _lock = TASKS_LOCK.lock().unwrap();
+        storage::load(TASKS_FILE)
+    }
+
+    /// Loads the task list, modifies it and saves it back in one go
+    pub fn update<T>(func: impl FnOnce(&mut TaskList) -> T) -> Result<T> {
+        let _lock = TASKS_LOCK.lock().unwrap();
+        let mut list: TaskList = storage::load(TASKS_FILE)?;
+        let res = func(&mut list);
+        storage::save(TASKS_FILE, &list)?;
+        Ok(res)
+    }
+
+    /// Credits a completed pomodoro to a task, returning its title
+    pub fn credit(id: Uuid) -> Result<Option<String>> {
+        Self::update(|list| list.get_mut(id).map(|task| {
+            task.completed = task.completed.saturating_add(1);
+            task.title.clone()
+        }))
+    }
+
+    pub fn add(&mut self, title: String, estimate: u8) -> Uuid {
+        let id = Uuid::new_v4();
+        self.tasks.push(Task { id, title, estimate, completed: 0, done: false, project: None, tags: Vec::new(), priority: None, source: None });
+        id
+    }
+
+    pub fn remove(&mut self, id: Uuid) {
+        self.tasks.retain(|x| x.id != id);
+    }
+
+    pub fn get(&self, id: Uuid) -> Option<&Task> {
+        self.tasks.iter().find(|x| x.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: Uuid) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|x| x.id == id)
+    }
+
+    /// Tasks that aren't done yet
+    pub fn open(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter().filter(|x| !x.done)
+    }
+}
@@ -0,0 +1,39 @@
+use std::{fs, ops::Deref, path::{Path, PathBuf}, sync::Mutex};
+
+use uuid::Uuid;
+
+use crate::storage::DATA_DIR_ENV;
+
+/// The data directory comes from the environment, so tests using it take turns
+static DATA_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+/// An empty folder of its own in the temp directory, removed once dropped, failed tests included
+pub struct TempDir(PathBuf);
+
+pub fn temp_dir() -> TempDir {
+    let dir = std::env::temp_dir().join(format!("overfocus-test-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    TempDir(dir)
+}
+
+/// Runs `func` with an empty data directory of its own
+pub fn with_data_dir(func: impl FnOnce()) {
+    let _lock = DATA_DIR_LOCK.lock().unwrap_or_else(|x| x.into_inner());
+    let dir = temp_dir();
+    std::env::set_var(DATA_DIR_ENV, &*dir);
+    func();
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}